@@ -0,0 +1,136 @@
+// Time and memory of each phase of a run (read, peak detection, sort,
+// sweep) on synthetic diamond-square terrain, from 5x5 up to a full
+// 6000x4800 GTOPO30 tile.
+//
+//     cargo bench --bench phases [-- --size <rows>x<cols>] [--csv]
+//
+// The grid is written as .bin beforehand, so "read" covers the binary
+// reader, keeping i16 samples as `compute` does. Small grids are run
+// repeatedly and the fastest run of each phase is kept. Peak RSS is the
+// high-water mark during the phase where the kernel lets it be reset,
+// and since the start of the process otherwise.
+
+use std::time::Instant;
+
+use topographic_prominence::synth::{generate, TerrainOptions};
+use topographic_prominence::timing::{PhaseTiming, Timings};
+use topographic_prominence::{
+    compute_prominence_with, write_grid, Monitor, Phase, ProminenceOptions, Registry,
+};
+
+const SIZES: [(usize, usize); 6] = [
+    (5, 5),
+    (64, 64),
+    (512, 512),
+    (1200, 1200),
+    (3000, 2400),
+    (6000, 4800),
+];
+
+// Repeat runs until this many cells have been processed in total, up to
+// MAX_RUNS times
+const CELLS_PER_SIZE: usize = 4_000_000;
+const MAX_RUNS: usize = 1000;
+
+fn main() {
+    let mut sizes = SIZES.to_vec();
+    let mut csv = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--size" {
+            let value = args.next().expect("--size needs a value");
+            let (rows, cols) = value.split_once('x').unwrap_or((&value, &value));
+            sizes = vec![(rows.parse().unwrap(), cols.parse().unwrap())];
+        } else if arg == "--csv" {
+            csv = true;
+        }
+        // Other arguments (such as cargo's --bench) are ignored
+    }
+
+    if csv {
+        println!("rows,cols,phase,seconds,cells_per_second,peak_rss_kb");
+    } else {
+        println!(
+            "{:>11} {:<15} {:>10} {:>13} {:>12}",
+            "size", "phase", "seconds", "cells/s", "peak RSS MB"
+        );
+    }
+    let dir = std::env::temp_dir();
+    for (rows, cols) in sizes {
+        let grid = generate(&TerrainOptions {
+            rows,
+            cols,
+            ..TerrainOptions::default()
+        })
+        .unwrap();
+        let path = dir.join(format!(
+            "phases-{}-{}x{}.bin",
+            std::process::id(),
+            rows,
+            cols
+        ));
+        let path = path.to_str().unwrap().to_string();
+        write_grid(&path, &grid, None, None).unwrap();
+        drop(grid);
+
+        let runs = (CELLS_PER_SIZE / (rows * cols)).clamp(1, MAX_RUNS);
+        let mut best: Vec<(&'static str, f64, Option<u64>)> = Vec::new();
+        let start = Instant::now();
+        for _ in 0..runs {
+            for phase in run(&path) {
+                match best.iter_mut().find(|(name, _, _)| *name == phase.name) {
+                    Some(entry) => {
+                        entry.1 = entry.1.min(phase.seconds);
+                        entry.2 = entry.2.max(phase.peak_rss_kb);
+                    }
+                    None => best.push((phase.name, phase.seconds, phase.peak_rss_kb)),
+                }
+            }
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.replace(".bin", ".pat")).unwrap();
+
+        let cells = (rows * cols) as f64;
+        let size = format!("{}x{}", rows, cols);
+        for (name, seconds, rss) in &best {
+            if csv {
+                println!(
+                    "{},{},{},{:.6},{:.0},{}",
+                    rows,
+                    cols,
+                    name,
+                    seconds,
+                    cells / seconds,
+                    rss.map_or(String::new(), |kb| kb.to_string())
+                );
+            } else {
+                println!(
+                    "{:>11} {:<15} {:>10.6} {:>13.3e} {:>12}",
+                    size,
+                    name,
+                    seconds,
+                    cells / seconds,
+                    rss.map_or("-".to_string(), |kb| format!("{:.1}", kb as f64 / 1024.0))
+                );
+            }
+        }
+        if !csv {
+            println!("{:>11} {} run(s) in {:.2} s", "", runs, elapsed);
+        }
+    }
+}
+
+// One read and prominence run of the grid at `path`, timed per phase
+fn run(path: &str) -> Vec<PhaseTiming> {
+    let timings = Timings::with_rss_reset();
+    let observe = |phase, processed, total| timings.observe(phase, processed, total);
+    let monitor = Monitor::new().progress(&observe);
+
+    timings.begin(Phase::Read.name());
+    let mut source = Registry::default().open(path).unwrap();
+    let grid = source.read_compact_with(&monitor).unwrap();
+    let options = ProminenceOptions::new();
+    compute_prominence_with(&grid, &options, &monitor).unwrap();
+    timings.phases()
+}
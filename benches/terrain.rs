@@ -0,0 +1,63 @@
+// Prominence throughput on each kind of synthetic terrain.
+//
+//     cargo bench --bench terrain [-- <terrain name>...] [--size <rows>x<cols>]
+//
+// Prints generation and sweep times in cells per second.
+
+use std::time::Instant;
+
+use topographic_prominence::synth::{generate, Terrain, TerrainOptions};
+use topographic_prominence::{compute_prominence, ProminenceOptions};
+
+fn main() {
+    let mut sizes = vec![(256, 256), (1024, 1024), (2048, 2048)];
+    let mut kinds = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--size" {
+            let value = args.next().expect("--size needs a value");
+            let (rows, cols) = value.split_once('x').unwrap_or((&value, &value));
+            sizes = vec![(rows.parse().unwrap(), cols.parse().unwrap())];
+        } else if let Some(kind) = Terrain::from_name(&arg) {
+            kinds.push(kind);
+        }
+        // Other arguments (such as cargo's --bench) are ignored
+    }
+    if kinds.is_empty() {
+        kinds = Terrain::ALL.to_vec();
+    }
+
+    println!(
+        "{:<15} {:>11} {:>8} {:>12} {:>8} {:>13} {:>8}",
+        "terrain", "size", "gen s", "gen cells/s", "sweep s", "sweep cells/s", "peaks"
+    );
+    for &(rows, cols) in &sizes {
+        for &terrain in &kinds {
+            let options = TerrainOptions {
+                terrain,
+                rows,
+                cols,
+                ..TerrainOptions::default()
+            };
+            let start = Instant::now();
+            let grid = generate(&options).unwrap();
+            let generated = start.elapsed().as_secs_f64();
+
+            let start = Instant::now();
+            let result = compute_prominence(&grid, &ProminenceOptions::new().unlimited());
+            let swept = start.elapsed().as_secs_f64();
+
+            let cells = grid.len() as f64;
+            println!(
+                "{:<15} {:>11} {:>8.3} {:>12.3e} {:>8.3} {:>13.3e} {:>8}",
+                terrain.name(),
+                format!("{}x{}", rows, cols),
+                generated,
+                cells / generated,
+                swept,
+                cells / swept,
+                result.peaks.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use crate::error::{Error, Result};
+use crate::json::{self, Json};
+use crate::output::{self, json_string, OutputFormat, RunInfo};
+use crate::progress::{CancelToken, Monitor};
+use crate::prominence::{compute_prominence_with, ProminenceOptions};
+use crate::source::Registry;
+
+// Record of finished tiles in the output directory, one JSON object per
+// line, appended as tiles finish so an interrupted run can resume
+pub const JOURNAL: &str = "batch.jsonl";
+
+// Per-tile timings and outcomes of the whole run, rewritten at the end
+pub const SUMMARY: &str = "summary.csv";
+
+// Parameters of a batch run
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchOptions {
+    // Tiles processed at once. A full 6000x4800 tile needs about 0.5 GB.
+    pub jobs: usize,
+    pub format: OutputFormat,
+    pub prominence: ProminenceOptions,
+    // Skip tiles an earlier run into the same directory finished with the
+    // same format, peak limit and minimum prominence
+    pub resume: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            jobs: thread::available_parallelism().map_or(1, |n| n.get().min(4)),
+            format: OutputFormat::Text,
+            prominence: ProminenceOptions::default(),
+            resume: true,
+        }
+    }
+}
+
+// Outcome of one tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileReport {
+    // File stem, e.g. `W100N40`, naming the result file
+    pub name: String,
+    pub input: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub peaks: usize,
+    pub read_seconds: f64,
+    pub compute_seconds: f64,
+    // Including writing the results
+    pub total_seconds: f64,
+    // Why the tile failed; None if it succeeded
+    pub error: Option<String>,
+    // Finished by an earlier run and skipped in this one
+    pub resumed: bool,
+}
+
+impl TileReport {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn failed(name: &str, input: &str, error: String, seconds: f64) -> TileReport {
+        TileReport {
+            name: name.to_string(),
+            input: input.to_string(),
+            rows: 0,
+            cols: 0,
+            peaks: 0,
+            read_seconds: 0.0,
+            compute_seconds: 0.0,
+            total_seconds: seconds,
+            error: Some(error),
+            resumed: false,
+        }
+    }
+
+    // Journal line, with the options the results were computed with
+    fn write_json<W: Write>(&self, out: &mut W, settings: &Settings) -> io::Result<()> {
+        writeln!(
+            out,
+            "{{\"name\": {}, \"input\": {}, \"rows\": {}, \"cols\": {}, \"peaks\": {}, \
+             \"read_seconds\": {:.6}, \"compute_seconds\": {:.6}, \"total_seconds\": {:.6}, \
+             \"error\": {}, \"format\": {}, \"max_peaks\": {}, \"min_prominence\": {}}}",
+            json_string(&self.name),
+            json_string(&self.input),
+            self.rows,
+            self.cols,
+            self.peaks,
+            self.read_seconds,
+            self.compute_seconds,
+            self.total_seconds,
+            self.error
+                .as_deref()
+                .map_or("null".to_string(), json_string),
+            json_string(settings.format.extension()),
+            settings
+                .max_peaks
+                .map_or("null".to_string(), |n| n.to_string()),
+            settings.min_prominence
+        )
+    }
+
+    fn from_json(value: &Json) -> Option<TileReport> {
+        let number = |key: &str| value.get(key).and_then(Json::as_f64);
+        Some(TileReport {
+            name: value.get("name")?.as_str()?.to_string(),
+            input: value.get("input")?.as_str()?.to_string(),
+            rows: number("rows")? as usize,
+            cols: number("cols")? as usize,
+            peaks: number("peaks")? as usize,
+            read_seconds: number("read_seconds")?,
+            compute_seconds: number("compute_seconds")?,
+            total_seconds: number("total_seconds")?,
+            error: value.get("error")?.as_str().map(str::to_string),
+            resumed: true,
+        })
+    }
+}
+
+// Options a tile's results depend on, recorded with it in the journal so
+// that a run with other options computes the tile again
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Settings {
+    format: OutputFormat,
+    max_peaks: Option<usize>,
+    min_prominence: i32,
+}
+
+impl Settings {
+    fn new(options: &BatchOptions) -> Settings {
+        Settings {
+            format: options.format,
+            max_peaks: options.prominence.get_max_peaks(),
+            min_prominence: options.prominence.get_min_prominence(),
+        }
+    }
+
+    // None for entries written without them
+    fn from_json(value: &Json) -> Option<Settings> {
+        let format = value.get("format")?.as_str()?;
+        let max_peaks = value.get("max_peaks")?;
+        Some(Settings {
+            format: OutputFormat::from_name(format)?,
+            max_peaks: if max_peaks.is_null() {
+                None
+            } else {
+                Some(max_peaks.as_f64()? as usize)
+            },
+            min_prominence: value.get("min_prominence")?.as_f64()? as i32,
+        })
+    }
+}
+
+// Outcome of a batch run, tiles in input order
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchSummary {
+    pub tiles: Vec<TileReport>,
+    pub seconds: f64,
+}
+
+impl BatchSummary {
+    pub fn failures(&self) -> impl Iterator<Item = &TileReport> {
+        self.tiles.iter().filter(|tile| !tile.succeeded())
+    }
+
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "tile,input,status,rows,cols,peaks,read_seconds,compute_seconds,total_seconds,error"
+        )?;
+        for tile in &self.tiles {
+            let status = match (tile.succeeded(), tile.resumed) {
+                (true, false) => "ok",
+                (true, true) => "resumed",
+                (false, _) => "failed",
+            };
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{:.3},{:.3},{:.3},{}",
+                csv_field(&tile.name),
+                csv_field(&tile.input),
+                status,
+                tile.rows,
+                tile.cols,
+                tile.peaks,
+                tile.read_seconds,
+                tile.compute_seconds,
+                tile.total_seconds,
+                csv_field(tile.error.as_deref().unwrap_or(""))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Quoted when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Tiles named by `path`: the readable grids in a directory, by file name,
+// or the files listed in a manifest, one per line. Manifest paths are
+// relative to the manifest; blank lines and `#` comments are skipped.
+pub fn list_tiles(path: &str) -> Result<Vec<String>> {
+    let root = Path::new(path);
+    let mut tiles = Vec::new();
+    if root.is_dir() {
+        let registry = Registry::default();
+        let extensions: Vec<&str> = registry
+            .formats()
+            .flat_map(|format| format.extensions().iter().copied())
+            .collect();
+        for entry in fs::read_dir(root).map_err(|e| Error::io(path, e))? {
+            let entry_path = entry.map_err(|e| Error::io(path, e))?.path();
+            let known = entry_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()));
+            if known && entry_path.is_file() {
+                tiles.push(entry_path.to_string_lossy().into_owned());
+            }
+        }
+        tiles.sort();
+    } else {
+        let manifest = fs::read_to_string(root).map_err(|e| Error::io(path, e))?;
+        let base = root.parent().unwrap_or(Path::new(""));
+        for line in manifest.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                tiles.push(base.join(line).to_string_lossy().into_owned());
+            }
+        }
+    }
+    if tiles.is_empty() {
+        return Err(Error::InvalidParameter(format!("no tiles in '{}'", path)));
+    }
+    Ok(tiles)
+}
+
+// Name of a tile's result file: its file stem
+pub fn tile_name(input: &str) -> String {
+    Path::new(input).file_stem().map_or_else(
+        || input.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+// Compute the prominence of every tile, up to `options.jobs` at a time,
+// writing each tile's results to `out_dir` and appending its outcome to the
+// journal there. A failing tile is reported and the others carry on.
+// `on_tile` sees each tile as it finishes. Cancelling `cancel` stops the
+// tiles in progress; finished ones stay journaled for a later resume.
+pub fn run_batch<F: FnMut(&TileReport)>(
+    tiles: &[String],
+    out_dir: &str,
+    options: &BatchOptions,
+    cancel: &CancelToken,
+    mut on_tile: F,
+) -> Result<BatchSummary> {
+    let start = Instant::now();
+    let mut names = HashMap::new();
+    for tile in tiles {
+        if let Some(other) = names.insert(tile_name(tile), tile) {
+            return Err(Error::InvalidParameter(format!(
+                "tiles '{}' and '{}' would write the same results",
+                other, tile
+            )));
+        }
+    }
+    fs::create_dir_all(out_dir).map_err(|e| Error::io(out_dir, e))?;
+    let journal_path = Path::new(out_dir).join(JOURNAL);
+    let journal_name = journal_path.to_string_lossy().into_owned();
+
+    // Tiles finished earlier with the same options, if their results are
+    // still there
+    let settings = Settings::new(options);
+    let mut finished: HashMap<String, TileReport> = HashMap::new();
+    if options.resume {
+        for (report, recorded) in read_journal(&journal_path)? {
+            let current = recorded == Some(settings);
+            if !current && report.succeeded() {
+                crate::debug!("{} was computed with other options", report.input);
+            }
+            if current
+                && report.succeeded()
+                && result_path(out_dir, &report.name, options.format).is_file()
+            {
+                finished.insert(report.input.clone(), report);
+            } else {
+                finished.remove(&report.input);
+            }
+        }
+    }
+    let mut journal = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(options.resume)
+        .truncate(!options.resume)
+        .open(&journal_path)
+        .map_err(|e| Error::io(&journal_name, e))?;
+
+    let mut reports: Vec<Option<TileReport>> = tiles
+        .iter()
+        .map(|tile| finished.remove(tile.as_str()))
+        .collect();
+    for report in reports.iter().flatten() {
+        on_tile(report);
+    }
+    let pending: Vec<usize> = (0..tiles.len()).filter(|&i| reports[i].is_none()).collect();
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let journal_result = thread::scope(|scope| -> Result<()> {
+        for _ in 0..options.jobs.clamp(1, pending.len().max(1)) {
+            let sender = sender.clone();
+            let (next, pending) = (&next, &pending);
+            scope.spawn(move || {
+                while let Some(&index) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let input = &tiles[index];
+                    let tile_start = Instant::now();
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                        process_tile(input, out_dir, options, cancel)
+                    }));
+                    let report = match outcome {
+                        Ok(Ok(report)) => report,
+                        Ok(Err(Error::Cancelled)) => break,
+                        Ok(Err(error)) => TileReport::failed(
+                            &tile_name(input),
+                            input,
+                            error.to_string(),
+                            tile_start.elapsed().as_secs_f64(),
+                        ),
+                        Err(_) => TileReport::failed(
+                            &tile_name(input),
+                            input,
+                            "panicked".to_string(),
+                            tile_start.elapsed().as_secs_f64(),
+                        ),
+                    };
+                    if sender.send((index, report)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Journal on this thread only, one whole line per tile
+        for (index, report) in receiver {
+            let mut line = Vec::new();
+            report
+                .write_json(&mut line, &settings)
+                .and_then(|_| journal.write_all(&line))
+                .map_err(|e| Error::io(&journal_name, e))?;
+            on_tile(&report);
+            reports[index] = Some(report);
+        }
+        Ok(())
+    });
+    journal_result?;
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let summary = BatchSummary {
+        tiles: reports.into_iter().flatten().collect(),
+        seconds: start.elapsed().as_secs_f64(),
+    };
+    let summary_path = Path::new(out_dir).join(SUMMARY);
+    let summary_name = summary_path.to_string_lossy().into_owned();
+    let mut out =
+        BufWriter::new(File::create(&summary_path).map_err(|e| Error::io(&summary_name, e))?);
+    summary
+        .write_csv(&mut out)
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::io(&summary_name, e))?;
+    Ok(summary)
+}
+
+fn result_path(out_dir: &str, name: &str, format: OutputFormat) -> PathBuf {
+    Path::new(out_dir).join(format!("{}.{}", name, format.extension()))
+}
+
+// Journal entries in order with the options they were computed with; a line
+// cut short by an interruption is ignored
+fn read_journal(path: &Path) -> Result<Vec<(TileReport, Option<Settings>)>> {
+    let name = path.to_string_lossy();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io(&name, e)),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| json::parse(&name, line).ok())
+        .filter_map(|value| Some((TileReport::from_json(&value)?, Settings::from_json(&value))))
+        .collect())
+}
+
+// Read, compute and write one tile. Results go to a temporary file first,
+// so a result file present after an interruption is complete.
+fn process_tile(
+    input: &str,
+    out_dir: &str,
+    options: &BatchOptions,
+    cancel: &CancelToken,
+) -> Result<TileReport> {
+    let start = Instant::now();
+    let monitor = Monitor::new().cancel_token(cancel);
+    let mut source = Registry::default().open(input)?;
+    let georef = source.georef();
+    let grid = source.read_compact_with(&monitor)?;
+    let read_seconds = start.elapsed().as_secs_f64();
+
+    let computed = Instant::now();
+    let peaks = compute_prominence_with(&grid, &options.prominence, &monitor)?.peaks;
+    let compute_seconds = computed.elapsed().as_secs_f64();
+
+    let name = tile_name(input);
+    let path = result_path(out_dir, &name, options.format);
+    let partial = path.with_extension(format!("{}.partial", options.format.extension()));
+    let partial_name = partial.to_string_lossy().into_owned();
+    let info = RunInfo {
+        input,
+        rows: grid.rows,
+        cols: grid.cols,
+        max_peaks: options.prominence.get_max_peaks(),
+        min_prominence: options.prominence.get_min_prominence(),
+        georef,
+        window: None,
+    };
+    let mut out = BufWriter::new(File::create(&partial).map_err(|e| Error::io(&partial_name, e))?);
+    output::write_peaks(&mut out, options.format, &peaks, &info)
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::io(&partial_name, e))?;
+    drop(out);
+    fs::rename(&partial, &path).map_err(|e| Error::io(&partial_name, e))?;
+
+    Ok(TileReport {
+        name,
+        input: input.to_string(),
+        rows: grid.rows,
+        cols: grid.cols,
+        peaks: peaks.len(),
+        read_seconds,
+        compute_seconds,
+        total_seconds: start.elapsed().as_secs_f64(),
+        error: None,
+        resumed: false,
+    })
+}
@@ -0,0 +1,126 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::grid::Elevations;
+use crate::merge_tree::MergeTree;
+use crate::prominence::{outranks, NEIGHBOURS};
+
+// The highest saddle between two cells: the lowest point that every route
+// between them must descend to, at the place where the best route does
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bottleneck {
+    pub row: usize,
+    pub col: usize,
+    pub elevation: i32,
+}
+
+// Highest saddle between cells `from` and `to`, given as (row, col) within
+// the grid that `tree` was recorded for: the cell whose activation first put
+// both in one set. A cell lower than any way onwards is its own bottleneck.
+// None when the two are never joined, as when one was left out of the sweep
+// by `ProminenceOptions::within` or the region's parts are apart.
+pub fn bottleneck<G: Elevations + ?Sized>(
+    tree: &MergeTree,
+    grid: &G,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Option<Bottleneck> {
+    let cols = tree.cols;
+    let (a, b) = (from.0 * cols + from.1, to.0 * cols + to.1);
+    if tree.summit(a).is_none() || tree.summit(b).is_none() {
+        return None;
+    }
+
+    // Sets that held `a`, each with the node whose link brought `a` in
+    let mut joined = HashMap::new();
+    let (mut node, mut via) = (a, None);
+    loop {
+        joined.insert(node, via);
+        if tree.link(node) == node {
+            break;
+        }
+        via = Some(node);
+        node = tree.link(node);
+    }
+
+    // The first of them to hold `b` too is where the two met
+    let (mut node, mut b_via) = (b, None);
+    let a_via = loop {
+        if let Some(&a_via) = joined.get(&node) {
+            break a_via;
+        }
+        if tree.link(node) == node {
+            return None;
+        }
+        b_via = Some(node);
+        node = tree.link(node);
+    };
+    // Of the two links into that set, the later one made the meeting: the
+    // one made by the cell swept last
+    let cell = match (a_via, b_via) {
+        (None, None) => a,
+        (Some(x), None) | (None, Some(x)) => tree.merged_by(x),
+        (Some(x), Some(y)) => {
+            let (x, y) = (tree.merged_by(x), tree.merged_by(y));
+            if outranks(grid, x, y) {
+                y
+            } else {
+                x
+            }
+        }
+    };
+    Some(Bottleneck {
+        row: cell / cols,
+        col: cell % cols,
+        elevation: grid.metres(cell),
+    })
+}
+
+// Fewest-step route from `from` to `to` through cells no lower than `floor`,
+// moving between the eight neighbours of a cell as the sweep does. With the
+// bottleneck's elevation as `floor` this follows the ridge between the two,
+// descending only as far as it must. None when there is no such route.
+pub fn ridge_path<G: Elevations + ?Sized>(
+    grid: &G,
+    from: (usize, usize),
+    to: (usize, usize),
+    floor: i32,
+) -> Option<Vec<(usize, usize)>> {
+    let (rows, cols) = grid.dimensions();
+    let (start, end) = (from.0 * cols + from.1, to.0 * cols + to.1);
+    if grid.metres(start) < floor || grid.metres(end) < floor {
+        return None;
+    }
+
+    // Search back from `to`, so that following `next` from `from` leads there
+    let mut next = vec![u32::MAX; rows * cols];
+    next[end] = end as u32;
+    let mut queue = VecDeque::from([end]);
+    while let Some(cell) = queue.pop_front() {
+        if cell == start {
+            break;
+        }
+        let (x, y) = (cell / cols, cell % cols);
+        for &(dx, dy) in &NEIGHBOURS {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx >= 0 && nx < rows as i32 && ny >= 0 && ny < cols as i32 {
+                let neighbour = (nx as usize) * cols + ny as usize;
+                if next[neighbour] == u32::MAX && grid.metres(neighbour) >= floor {
+                    next[neighbour] = cell as u32;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+    if next[start] == u32::MAX {
+        return None;
+    }
+
+    let mut path = vec![from];
+    let mut cell = start;
+    while cell != end {
+        cell = next[cell] as usize;
+        path.push((cell / cols, cell % cols));
+    }
+    Some(path)
+}
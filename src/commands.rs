@@ -0,0 +1,354 @@
+use std::cell::Cell;
+use std::io::{self, IsTerminal, Write};
+use std::process;
+use std::str::FromStr;
+
+use topographic_prominence::log::{self, Level};
+use topographic_prominence::timing::Timings;
+use topographic_prominence::{
+    CancelToken, CompactGrid, Error, Georef, Monitor, Phase, Registry, Result, Window,
+};
+
+pub mod batch;
+pub mod bottleneck;
+pub mod compute;
+pub mod convert;
+pub mod diff;
+pub mod generate;
+pub mod index;
+pub mod info;
+pub mod query;
+pub mod render;
+
+// Options accepted by every command, appended to their usage text
+pub const COMMON_OPTIONS: &str = "\
+Common options:
+  -v, -vv            log details (info, debug) to stderr
+  -q                 only log errors; implies --no-progress
+  --no-progress      no progress bar (shown by default on a terminal)
+  --timings          print wall time and peak memory per phase as JSON on stderr
+  -h, --help         show this help";
+
+// Arguments of one command. `--help` prints the command's usage and exits.
+pub struct Args {
+    args: std::vec::IntoIter<String>,
+    usage: &'static str,
+}
+
+impl Args {
+    pub fn new(args: Vec<String>, usage: &'static str) -> Args {
+        Args {
+            args: args.into_iter(),
+            usage,
+        }
+    }
+
+    pub fn next(&mut self) -> Option<String> {
+        let arg = self.args.next()?;
+        if arg == "-h" || arg == "--help" {
+            let _ = writeln!(io::stdout(), "{}\n\n{}", self.usage, COMMON_OPTIONS);
+            process::exit(0);
+        }
+        Some(arg)
+    }
+
+    // Value of `--name value` or `--name=value`, if `arg` is that option
+    pub fn value(&mut self, arg: &str, name: &str) -> Result<Option<String>> {
+        if arg == name {
+            self.args
+                .next()
+                .map(Some)
+                .ok_or_else(|| Error::InvalidParameter(format!("{} needs a value", name)))
+        } else {
+            Ok(arg
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(str::to_string))
+        }
+    }
+
+    // Parsed value of option `name`, if `arg` is that option
+    pub fn parsed<T: FromStr>(&mut self, arg: &str, name: &str) -> Result<Option<T>> {
+        self.value(arg, name)?
+            .map(|value| parse_value(name, &value))
+            .transpose()
+    }
+
+    // Report an unexpected or missing argument and exit with the usage code
+    pub fn usage_error(&self, message: &str) -> ! {
+        eprintln!("{}\n\n{}\n\n{}", message, self.usage, COMMON_OPTIONS);
+        process::exit(2);
+    }
+}
+
+// Parse the value of option `name`, reporting bad values as invalid parameters
+pub fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidParameter(format!("{} '{}'", name, value)))
+}
+
+// `<row>,<col>` of a cell
+pub fn parse_cell(name: &str, value: &str) -> Result<(usize, usize)> {
+    let (row, col) = value
+        .split_once(',')
+        .ok_or_else(|| Error::InvalidParameter(format!("{} '{}'", name, value)))?;
+    Ok((
+        parse_value(name, row.trim())?,
+        parse_value(name, col.trim())?,
+    ))
+}
+
+// Logging, progress bar and timing flags shared by all commands
+pub struct Reporting {
+    show_progress: bool,
+    show_timings: bool,
+    bar: ProgressBar,
+    pub timings: Timings,
+}
+
+impl Reporting {
+    pub fn new() -> Reporting {
+        Reporting {
+            show_progress: io::stderr().is_terminal(),
+            show_timings: false,
+            bar: ProgressBar::new(),
+            timings: Timings::new(),
+        }
+    }
+
+    // Apply `arg` if it is one of the common options
+    pub fn parse(&mut self, arg: &str) -> bool {
+        match arg {
+            "-v" | "--verbose" => log::set_max_level(Level::Info),
+            "-vv" => log::set_max_level(Level::Debug),
+            "-q" | "--quiet" => {
+                log::set_max_level(Level::Error);
+                self.show_progress = false;
+            }
+            "--no-progress" => self.show_progress = false,
+            "--timings" => {
+                self.show_timings = true;
+                self.timings = Timings::with_rss_reset();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // Progress callback for a `Monitor`
+    pub fn update(&self, phase: Phase, processed: usize, total: usize) {
+        if self.show_progress {
+            self.bar.update(phase, processed, total);
+        }
+        self.timings.observe(phase, processed, total);
+    }
+
+    pub fn finish_progress(&self) {
+        self.bar.finish();
+    }
+
+    // End the run, printing the timings when requested
+    pub fn finish(&self) -> Result<()> {
+        self.bar.finish();
+        if self.show_timings {
+            self.timings.write_json(&mut io::stderr().lock())?;
+        }
+        Ok(())
+    }
+}
+
+// Read a grid at its native sample width with the built-in readers, timing
+// and reporting the read phase
+pub fn read_input(
+    path: &str,
+    reporting: &Reporting,
+    monitor: &Monitor,
+) -> Result<(CompactGrid, Option<Georef>)> {
+    reporting.timings.begin(Phase::Read.name());
+    let mut source = Registry::default().open(path)?;
+    let grid = source.read_compact_with(monitor)?;
+    Ok((grid, source.georef()))
+}
+
+// Part of the input a command works on, as given on the command line
+pub enum Crop {
+    // Inclusive (first, last) rows and columns
+    Cells((usize, usize), (usize, usize)),
+    // West, south, east and north edges in degrees
+    BBox(f64, f64, f64, f64),
+}
+
+impl Crop {
+    // `<first row>:<last row>,<first col>:<last col>`, inclusive
+    pub fn parse_cells(name: &str, value: &str) -> Result<Crop> {
+        let range = |part: &str| -> Result<(usize, usize)> {
+            let (first, last) = part
+                .split_once(':')
+                .ok_or_else(|| Error::InvalidParameter(format!("{} '{}'", name, value)))?;
+            Ok((parse_value(name, first)?, parse_value(name, last)?))
+        };
+        let (rows, cols) = value
+            .split_once(',')
+            .ok_or_else(|| Error::InvalidParameter(format!("{} '{}'", name, value)))?;
+        Ok(Crop::Cells(range(rows)?, range(cols)?))
+    }
+
+    // `<west>,<south>,<east>,<north>`
+    pub fn parse_bbox(name: &str, value: &str) -> Result<Crop> {
+        let edges = value
+            .split(',')
+            .map(|edge| parse_value(name, edge.trim()))
+            .collect::<Result<Vec<f64>>>()?;
+        match edges[..] {
+            [west, south, east, north] => Ok(Crop::BBox(west, south, east, north)),
+            _ => Err(Error::InvalidParameter(format!("{} '{}'", name, value))),
+        }
+    }
+
+    fn window(&self, georef: Option<&Georef>, dimensions: (usize, usize)) -> Result<Window> {
+        match *self {
+            Crop::Cells(rows, cols) => Window::from_ranges(rows, cols, dimensions),
+            Crop::BBox(west, south, east, north) => {
+                let georef = georef.ok_or_else(|| {
+                    Error::InvalidParameter(
+                        "--bbox needs a georeferenced grid; use --window".to_string(),
+                    )
+                })?;
+                Window::from_bbox(georef, (west, south, east, north), dimensions)
+            }
+        }
+    }
+}
+
+// Window of a grid together with the whole grid's size and georeference
+pub struct CroppedInput {
+    pub grid: CompactGrid,
+    pub window: Window,
+    pub size: (usize, usize),
+    pub georef: Option<Georef>,
+}
+
+// Read only the `crop` part of a grid, timing the read phase
+pub fn read_input_window(path: &str, crop: &Crop, reporting: &Reporting) -> Result<CroppedInput> {
+    reporting.timings.begin(Phase::Read.name());
+    let mut source = Registry::default().open(path)?;
+    let georef = source.georef();
+    let dimensions = source.dimensions();
+    let window = crop.window(georef.as_ref(), dimensions)?;
+    topographic_prominence::info!(
+        "Reading {}x{} window at ({}, {}) of {}x{} grid",
+        window.rows,
+        window.cols,
+        window.row,
+        window.col,
+        dimensions.0,
+        dimensions.1
+    );
+    Ok(CroppedInput {
+        grid: source.read_compact_window(&window)?,
+        window,
+        size: dimensions,
+        georef,
+    })
+}
+
+// Cancel `token` on the first Ctrl-C, so that a run can wind down cleanly;
+// a second Ctrl-C ends the process at once
+pub fn cancel_on_interrupt(token: &CancelToken) {
+    interrupt::install(token.clone());
+}
+
+#[cfg(unix)]
+mod interrupt {
+    use std::os::raw::c_int;
+    use std::sync::OnceLock;
+
+    use topographic_prominence::CancelToken;
+
+    const SIGINT: c_int = 2;
+    const SIG_DFL: usize = 0;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+    }
+
+    static TOKEN: OnceLock<CancelToken> = OnceLock::new();
+
+    // Only an atomic store and `signal`, both safe in a signal handler
+    extern "C" fn handle(_: c_int) {
+        if let Some(token) = TOKEN.get() {
+            token.cancel();
+        }
+        unsafe {
+            signal(SIGINT, SIG_DFL);
+        }
+    }
+
+    pub fn install(token: CancelToken) {
+        if TOKEN.set(token).is_ok() {
+            unsafe {
+                signal(SIGINT, handle as extern "C" fn(c_int) as usize);
+            }
+        }
+    }
+}
+
+// Ctrl-C keeps ending the process at once elsewhere
+#[cfg(not(unix))]
+mod interrupt {
+    use topographic_prominence::CancelToken;
+
+    pub fn install(_: CancelToken) {}
+}
+
+// Progress bar on stderr, redrawn when the percentage changes
+struct ProgressBar {
+    shown: Cell<Option<(Phase, usize)>>,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+
+    fn new() -> ProgressBar {
+        ProgressBar {
+            shown: Cell::new(None),
+        }
+    }
+
+    fn update(&self, phase: Phase, processed: usize, total: usize) {
+        let percent = (processed * 100).checked_div(total).unwrap_or(100);
+        let shown = self.shown.get();
+        if shown == Some((phase, percent)) {
+            return;
+        }
+        let mut err = io::stderr().lock();
+        if shown.is_some_and(|(last, _)| last != phase) {
+            let _ = writeln!(err);
+        }
+        let filled = percent * Self::WIDTH / 100;
+        let _ = write!(
+            err,
+            "\r{:<15}[{}{}] {:>3}%",
+            phase.name(),
+            "#".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            percent
+        );
+        let _ = err.flush();
+        self.shown.set(Some((phase, percent)));
+    }
+
+    fn finish(&self) {
+        if self.shown.take().is_some() {
+            eprintln!();
+        }
+    }
+}
+
+// End the bar's line even when the run fails part-way
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use topographic_prominence::batch::{self, BatchOptions, TileReport, JOURNAL, SUMMARY};
+use topographic_prominence::output::OutputFormat;
+use topographic_prominence::{CancelToken, Error, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence batch <directory|manifest> --out <directory> [options]
+
+Compute the prominence of every tile in a directory (each .bin, .csv or .asc
+file) or listed in a manifest (one path per line, relative to it; # starts
+a comment). Each tile's peaks are written to <out>/<tile>.<format>, and
+every tile is listed with its timings or failure in <out>/summary.csv.
+Finished tiles are recorded in <out>/batch.jsonl as they complete, so a run
+that was interrupted picks up where it stopped. Failed tiles, and tiles
+computed with another format, --max-peaks or --min-prominence, are run again.
+
+  --out <directory>       where results and the summary are written
+  --jobs <n>              tiles computed at once (default: cores, at most 4)
+  --format <name>         text, csv, json, geojson, kml or gpx (default text)
+  --max-peaks <n|all>     number of peaks reported per tile (default 100)
+  --min-prominence <m>    drop peaks with less prominence
+  --restart               compute every tile again, ignoring earlier runs
+
+Ctrl-C stops the tiles in progress without leaving partial results, and a
+later run resumes from the finished ones; a second Ctrl-C ends it at once.
+
+Exits with status 1 if any tile failed, 130 if interrupted.";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut input = None;
+    let mut out_dir = None;
+    let mut options = BatchOptions::default();
+    let mut min_prominence = 0;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--out")? {
+            out_dir = Some(value);
+        } else if let Some(value) = args.parsed(&arg, "--jobs")? {
+            if value == 0 {
+                return Err(Error::InvalidParameter("--jobs 0".to_string()));
+            }
+            options.jobs = value;
+        } else if let Some(value) = args.value(&arg, "--format")? {
+            options.format = OutputFormat::from_name(&value)
+                .ok_or_else(|| Error::UnsupportedFormat(format!("output format '{}'", value)))?;
+        } else if let Some(value) = args.value(&arg, "--max-peaks")? {
+            options.prominence = if value == "all" {
+                options.prominence.unlimited()
+            } else {
+                let n = super::parse_value("--max-peaks", &value)?;
+                options.prominence.max_peaks(n)
+            };
+        } else if let Some(value) = args.parsed(&arg, "--min-prominence")? {
+            min_prominence = value;
+        } else if arg == "--restart" {
+            options.resume = false;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || input.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            input = Some(arg);
+        }
+    }
+    let input = input.unwrap_or_else(|| args.usage_error("Missing tile directory or manifest"));
+    let out_dir = out_dir.unwrap_or_else(|| args.usage_error("Missing --out directory"));
+    options.prominence = options.prominence.min_prominence(min_prominence);
+    // Result files would otherwise be taken for tiles on the next run
+    if Path::new(&input).is_dir() && same_directory(&input, &out_dir) {
+        return Err(Error::InvalidParameter(
+            "--out must differ from the tile directory".to_string(),
+        ));
+    }
+
+    let tiles = batch::list_tiles(&input)?;
+    topographic_prominence::info!(
+        "Processing {} tiles, {} at a time",
+        tiles.len(),
+        options.jobs
+    );
+    reporting.timings.begin("batch");
+    let cancel = CancelToken::new();
+    super::cancel_on_interrupt(&cancel);
+    let stdout = io::stdout();
+    let mut done = 0;
+    let result = batch::run_batch(&tiles, &out_dir, &options, &cancel, |tile| {
+        done += 1;
+        let _ = writeln!(
+            stdout.lock(),
+            "[{}/{}] {}",
+            done,
+            tiles.len(),
+            describe(tile)
+        );
+    });
+    if let Err(Error::Cancelled) = result {
+        topographic_prominence::warn!(
+            "Interrupted; finished tiles are kept and a new run into {} resumes",
+            out_dir
+        );
+    }
+    let summary = result?;
+
+    let failures: Vec<&TileReport> = summary.failures().collect();
+    let mut out = stdout.lock();
+    writeln!(
+        out,
+        "{} tiles, {} failed, in {:.1} s; summary in {}",
+        summary.tiles.len(),
+        failures.len(),
+        summary.seconds,
+        Path::new(&out_dir).join(SUMMARY).display()
+    )?;
+    for tile in &failures {
+        writeln!(
+            out,
+            "  {}: {}",
+            tile.input,
+            tile.error.as_deref().unwrap_or("")
+        )?;
+    }
+    topographic_prominence::debug!(
+        "Journal kept in {}",
+        Path::new(&out_dir).join(JOURNAL).display()
+    );
+    reporting.finish()?;
+    Ok(if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn describe(tile: &TileReport) -> String {
+    match (&tile.error, tile.resumed) {
+        (Some(error), _) => format!("{} failed: {}", tile.name, error),
+        (None, true) => format!("{} done by an earlier run", tile.name),
+        (None, false) => format!(
+            "{} {}x{}, {} peaks in {:.2} s (read {:.2} s, compute {:.2} s)",
+            tile.name,
+            tile.rows,
+            tile.cols,
+            tile.peaks,
+            tile.total_seconds,
+            tile.read_seconds,
+            tile.compute_seconds
+        ),
+    }
+}
+
+fn same_directory(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::process::ExitCode;
+
+use topographic_prominence::bottleneck::{self, Bottleneck};
+use topographic_prominence::{
+    for_each_peak_with, Elevations, Error, Georef, Monitor, ProminenceOptions, Result,
+};
+
+use super::{read_input, Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence bottleneck <grid> --from <point> --to <point> [options]
+
+Find the highest saddle between two points: the lowest elevation that any
+route between them must descend to, and where the best route does.
+
+  --from <row>,<col>   start cell
+  --to <row>,<col>     end cell
+  --lonlat             give --from and --to as <lon>,<lat> instead
+  --path               also list a route along the ridge, the fewest cells
+                       that never descend below the saddle
+  --format <name>      text or json (default text)";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut filename = None;
+    let mut from = None;
+    let mut to = None;
+    let mut lonlat = false;
+    let mut with_path = false;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--from")? {
+            from = Some(value);
+        } else if let Some(value) = args.value(&arg, "--to")? {
+            to = Some(value);
+        } else if arg == "--lonlat" {
+            lonlat = true;
+        } else if arg == "--path" {
+            with_path = true;
+        } else if let Some(value) = args.value(&arg, "--format")? {
+            json = match value.as_str() {
+                "text" => false,
+                "json" => true,
+                _ => {
+                    return Err(Error::UnsupportedFormat(format!(
+                        "output format '{}'",
+                        value
+                    )))
+                }
+            };
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || filename.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            filename = Some(arg);
+        }
+    }
+    let filename = &filename.unwrap_or_else(|| args.usage_error("Missing input grid"));
+    let from = from.unwrap_or_else(|| args.usage_error("Missing --from point"));
+    let to = to.unwrap_or_else(|| args.usage_error("Missing --to point"));
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    let (grid, georef) = read_input(filename, &reporting, &monitor)?;
+    let (rows, cols) = grid.dimensions();
+    let locate = |name: &str, value: &str| -> Result<(usize, usize)> {
+        let (row, col) = if lonlat {
+            let georef = georef.as_ref().ok_or_else(|| {
+                Error::InvalidParameter(format!("{} in lon/lat needs a georeferenced grid", name))
+            })?;
+            let (lon, lat) = value
+                .split_once(',')
+                .ok_or_else(|| Error::InvalidParameter(format!("{} '{}'", name, value)))?;
+            let (lon, lat) = (
+                super::parse_value(name, lon.trim())?,
+                super::parse_value(name, lat.trim())?,
+            );
+            georef.cell_at(lon, lat, rows, cols).ok_or_else(|| {
+                Error::InvalidParameter(format!("{} '{}' lies outside the grid", name, value))
+            })?
+        } else {
+            super::parse_cell(name, value)?
+        };
+        if row >= rows || col >= cols {
+            return Err(Error::InvalidParameter(format!(
+                "{} '{}' lies outside the {}x{} grid",
+                name, value, rows, cols
+            )));
+        }
+        Ok((row, col))
+    };
+    let (from, to) = (locate("--from", &from)?, locate("--to", &to)?);
+
+    // Only the merges are needed, not the peaks
+    let options = ProminenceOptions::new()
+        .unlimited()
+        .min_prominence(i32::MAX)
+        .track_merges(true);
+    let tree = for_each_peak_with(&grid, &options, &monitor, |_| ControlFlow::Continue(()))?
+        .expect("merges are tracked");
+    reporting.finish_progress();
+
+    reporting.timings.begin("bottleneck");
+    let saddle = bottleneck::bottleneck(&tree, &grid, from, to)
+        .expect("every cell of a whole grid is joined in the end");
+    let path = with_path
+        .then(|| bottleneck::ridge_path(&grid, from, to, saddle.elevation))
+        .flatten();
+
+    reporting.timings.begin("output");
+    let point = Point {
+        grid: &grid,
+        georef: georef.as_ref(),
+    };
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    if json {
+        write_json(&mut out, &point, from, to, &saddle, path.as_deref())?;
+    } else {
+        write_text(&mut out, &point, from, to, &saddle, path.as_deref())?;
+    }
+    out.flush()?;
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// Describes cells of the grid, with their longitude/latitude if known
+struct Point<'a, G: ?Sized> {
+    grid: &'a G,
+    georef: Option<&'a Georef>,
+}
+
+impl<G: Elevations + ?Sized> Point<'_, G> {
+    fn elevation(&self, (row, col): (usize, usize)) -> i32 {
+        self.grid.metres(row * self.grid.dimensions().1 + col)
+    }
+
+    fn text(&self, cell: (usize, usize)) -> String {
+        let place = match self.georef {
+            Some(georef) => {
+                let (lon, lat) = georef.cell_center(cell.0, cell.1);
+                format!(" ({:.5}, {:.5})", lon, lat)
+            }
+            None => String::new(),
+        };
+        format!(
+            "row {}, col {}{}, {} m",
+            cell.0,
+            cell.1,
+            place,
+            self.elevation(cell)
+        )
+    }
+
+    fn json(&self, cell: (usize, usize)) -> String {
+        let place = match self.georef {
+            Some(georef) => {
+                let (lon, lat) = georef.cell_center(cell.0, cell.1);
+                format!(", \"lon\": {}, \"lat\": {}", lon, lat)
+            }
+            None => String::new(),
+        };
+        format!(
+            "{{\"row\": {}, \"col\": {}, \"elevation\": {}{}}}",
+            cell.0,
+            cell.1,
+            self.elevation(cell),
+            place
+        )
+    }
+}
+
+fn write_text<W: Write, G: Elevations + ?Sized>(
+    out: &mut W,
+    point: &Point<G>,
+    from: (usize, usize),
+    to: (usize, usize),
+    saddle: &Bottleneck,
+    path: Option<&[(usize, usize)]>,
+) -> io::Result<()> {
+    writeln!(out, "From:    {}", point.text(from))?;
+    writeln!(out, "To:      {}", point.text(to))?;
+    writeln!(out, "Saddle:  {}", point.text((saddle.row, saddle.col)))?;
+    if let Some(path) = path {
+        writeln!(out, "Path:    {} cells", path.len())?;
+        for &cell in path {
+            writeln!(out, "  {}", point.text(cell))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json<W: Write, G: Elevations + ?Sized>(
+    out: &mut W,
+    point: &Point<G>,
+    from: (usize, usize),
+    to: (usize, usize),
+    saddle: &Bottleneck,
+    path: Option<&[(usize, usize)]>,
+) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"from\": {},", point.json(from))?;
+    writeln!(out, "  \"to\": {},", point.json(to))?;
+    match path {
+        Some(path) => {
+            writeln!(
+                out,
+                "  \"saddle\": {},",
+                point.json((saddle.row, saddle.col))
+            )?;
+            writeln!(out, "  \"path\": [")?;
+            for (i, &cell) in path.iter().enumerate() {
+                let separator = if i + 1 < path.len() { "," } else { "" };
+                writeln!(out, "    {}{}", point.json(cell), separator)?;
+            }
+            writeln!(out, "  ]")?;
+        }
+        None => writeln!(
+            out,
+            "  \"saddle\": {}",
+            point.json((saddle.row, saddle.col))
+        )?,
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
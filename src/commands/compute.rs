@@ -0,0 +1,286 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use topographic_prominence::output::{self, OutputFormat, RunInfo};
+use topographic_prominence::raster::{self, Raster, RasterKind};
+use topographic_prominence::render::{self, RenderOptions, RenderStyle};
+use topographic_prominence::{
+    compute_prominence_with, domain, island, read_region, Error, Georef, Monitor, Prominence,
+    ProminenceOptions, Result,
+};
+
+use super::{read_input, read_input_window, Args, Crop, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence compute <grid> [options]
+
+Compute the prominence of every peak and print the top peaks on stdout.
+
+  --format <name>              text, csv, json, geojson, kml or gpx (default text)
+  --max-peaks <n|all>          number of peaks reported (default 100)
+  --min-prominence <m>         drop peaks with less prominence
+  --window <r0>:<r1>,<c0>:<c1> only rows r0..=r1 and columns c0..=c1
+  --bbox <w>,<s>,<e>,<n>       only cells centred in this lon/lat box
+  --region <file>              only peaks with summits in this GeoJSON or WKT
+                               polygon; cols may lie outside it
+  --within-region              make the region's boundary the edge of the
+                               world, so cols lie inside it too
+  --islands <file.geojson>     prominence-island polygons
+  --island-raster <raster>     cells labelled with their island's peak rank
+  --domains <raster>           cells labelled with their dominating peak's rank
+  --domain-stats <file.csv>    area, mean elevation and volume of each domain
+  --prominence-raster <raster> prominence at each reported summit
+  --render <map.png|map.svg>   map of the grid with the top peaks
+  --render-style <style>       hillshade or tint (default hillshade)
+  --render-size <px>           longest image side (default 1024)
+  --render-top <n>             peaks marked on the map (default 20)
+
+Rasters are written as .pgm, .png, .tif, .asc or .csv by extension.
+With --window or --bbox only that part of the grid is read. Peaks keep the
+full grid's coordinates and are flagged when their island reaches a side of
+the window that cuts through the grid, as their key col may lie beyond it;
+map products cover the window only.
+Region coordinates are longitude/latitude, or grid units as in the GeoJSON
+output when the grid has no georeference. A cell is in the region when its
+centre is.";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut filename = None;
+    let mut format = OutputFormat::Text;
+    let mut options = ProminenceOptions::new();
+    let mut islands_path = None;
+    let mut island_raster_path = None;
+    let mut domains_path = None;
+    let mut domain_stats_path = None;
+    let mut prominence_raster_path = None;
+    let mut render_path = None;
+    let mut render_options = RenderOptions::default();
+    let mut min_prominence = 0;
+    let mut crop = None;
+    let mut region_path = None;
+    let mut within_region = false;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--format")? {
+            format = OutputFormat::from_name(&value)
+                .ok_or_else(|| Error::UnsupportedFormat(format!("output format '{}'", value)))?;
+        } else if let Some(value) = args.value(&arg, "--max-peaks")? {
+            options = if value == "all" {
+                options.unlimited()
+            } else {
+                options.max_peaks(super::parse_value("--max-peaks", &value)?)
+            };
+        } else if let Some(value) = args.parsed(&arg, "--min-prominence")? {
+            min_prominence = value;
+        } else if let Some(value) = args.value(&arg, "--window")? {
+            crop = Some(Crop::parse_cells("--window", &value)?);
+        } else if let Some(value) = args.value(&arg, "--bbox")? {
+            crop = Some(Crop::parse_bbox("--bbox", &value)?);
+        } else if let Some(value) = args.value(&arg, "--region")? {
+            region_path = Some(value);
+        } else if arg == "--within-region" {
+            within_region = true;
+        } else if let Some(value) = args.value(&arg, "--islands")? {
+            islands_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--island-raster")? {
+            island_raster_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--domains")? {
+            domains_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--domain-stats")? {
+            domain_stats_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--prominence-raster")? {
+            prominence_raster_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--render")? {
+            render_path = Some(value);
+        } else if let Some(value) = args.value(&arg, "--render-style")? {
+            render_options.style = RenderStyle::from_name(&value)
+                .ok_or_else(|| Error::InvalidParameter(format!("--render-style '{}'", value)))?;
+        } else if let Some(value) = args.parsed(&arg, "--render-size")? {
+            render_options.size = value;
+        } else if let Some(value) = args.parsed(&arg, "--render-top")? {
+            render_options.top = value;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || filename.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            filename = Some(arg);
+        }
+    }
+    let filename = &filename.unwrap_or_else(|| args.usage_error("Missing input grid"));
+    if within_region && region_path.is_none() {
+        args.usage_error("--within-region needs --region");
+    }
+    let region = region_path.as_deref().map(read_region).transpose()?;
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    // Read grid, widened to i32 only if a product below needs it. A cropped
+    // grid is open on the sides that cut through the input.
+    let (compact, input_georef, input_size, window) = match &crop {
+        Some(crop) => {
+            let input = read_input_window(filename, crop, &reporting)?;
+            let (rows, cols) = input.size;
+            options = options.open_edges(input.window.open_edges(rows, cols));
+            (input.grid, input.georef, input.size, Some(input.window))
+        }
+        None => {
+            let (compact, georef) = read_input(filename, &reporting, &monitor)?;
+            let size = (compact.rows, compact.cols);
+            (compact, georef, size, None)
+        }
+    };
+    let (rows, cols) = (compact.rows, compact.cols);
+    // Georeference of the cells read, used by the map products
+    let georef = match (window, input_georef) {
+        (Some(window), Some(georef)) => Some(window.georef(&georef)),
+        _ => input_georef,
+    };
+
+    // Cells of the region. Outside it, cells either only carry cols or are
+    // left out of the sweep altogether.
+    let mask = region.map(|region| {
+        if georef.is_none() {
+            topographic_prominence::warn!(
+                "No georeference found for '{}'; reading the region in grid coordinates",
+                filename
+            );
+        }
+        region.mask(&georef.unwrap_or(Georef::GRID), rows, cols)
+    });
+    if let Some(mask) = mask.as_ref().filter(|_| within_region) {
+        options = options.within(mask.clone());
+    }
+
+    // Compute prominence
+    let track_merges = islands_path.is_some()
+        || island_raster_path.is_some()
+        || domains_path.is_some()
+        || domain_stats_path.is_some();
+    let options = options
+        .min_prominence(min_prominence)
+        .track_merges(track_merges);
+    let Prominence { mut peaks, merges } = match &mask {
+        // Every peak is needed to pick the top ones inside the region
+        Some(mask) if !within_region => {
+            let all = options.clone().unlimited();
+            let mut result = compute_prominence_with(&compact, &all, &monitor)?;
+            result
+                .peaks
+                .retain(|peak| mask.contains(peak.peak_x * cols + peak.peak_y));
+            result
+                .peaks
+                .truncate(options.get_max_peaks().unwrap_or(usize::MAX));
+            result
+        }
+        _ => compute_prominence_with(&compact, &options, &monitor)?,
+    };
+    reporting.finish_progress();
+
+    reporting.timings.begin("output");
+    if georef.is_none()
+        && (islands_path.is_some()
+            || matches!(
+                format,
+                OutputFormat::GeoJson | OutputFormat::Kml | OutputFormat::Gpx
+            ))
+    {
+        topographic_prominence::warn!(
+            "No georeference found for '{}'; using grid coordinates",
+            filename
+        );
+    }
+
+    let raster = |values: Vec<u32>, kind: RasterKind| Raster {
+        rows,
+        cols,
+        values,
+        georef,
+        kind,
+        no_data: Some(raster::NO_DATA),
+    };
+
+    // Prominence islands and domains
+    if let Some(tree) = &merges {
+        if domains_path.is_some() || domain_stats_path.is_some() {
+            let labels = domain::label_domains(tree, &peaks);
+            if let Some(path) = &domain_stats_path {
+                let stats = domain::domain_stats(&compact, &peaks, &labels, georef.as_ref());
+                let mut out =
+                    io::BufWriter::new(File::create(path).map_err(|e| Error::io(path, e))?);
+                domain::write_domain_stats_csv(&mut out, &peaks, &stats)
+                    .and_then(|_| out.flush())
+                    .map_err(|e| Error::io(path, e))?;
+            }
+            if let Some(path) = &domains_path {
+                raster::write_raster(path, &raster(labels, RasterKind::Labels))?;
+            }
+        }
+
+        if islands_path.is_some() || island_raster_path.is_some() {
+            let mut labels = island::label_islands(tree, &compact, &peaks);
+            // Cells left out of the sweep belong to no island
+            if let Some(mask) = mask.as_ref().filter(|_| within_region) {
+                for (cell, label) in labels.iter_mut().enumerate() {
+                    if !mask.contains(cell) {
+                        *label = 0;
+                    }
+                }
+            }
+            if let Some(path) = &islands_path {
+                let islands =
+                    island::trace_islands(tree, &compact, &peaks, &labels, georef.as_ref());
+                let mut out =
+                    io::BufWriter::new(File::create(path).map_err(|e| Error::io(path, e))?);
+                island::write_islands_geojson(
+                    &mut out,
+                    &islands,
+                    &peaks,
+                    &georef.unwrap_or(Georef::GRID),
+                )
+                .and_then(|_| out.flush())
+                .map_err(|e| Error::io(path, e))?;
+            }
+            if let Some(path) = &island_raster_path {
+                raster::write_raster(path, &raster(labels, RasterKind::Labels))?;
+            }
+        }
+    }
+
+    // Prominence at each reported summit, 0 elsewhere and where it is
+    // negative, as for a highest summit below sea level
+    if let Some(path) = &prominence_raster_path {
+        let mut values = vec![0u32; rows * cols];
+        for peak in &peaks {
+            values[peak.peak_x * cols + peak.peak_y] = u32::try_from(peak.prominence).unwrap_or(0);
+        }
+        raster::write_raster(path, &raster(values, RasterKind::Values))?;
+    }
+
+    if let Some(path) = &render_path {
+        render::render_map(path, &compact, &peaks, georef.as_ref(), &render_options)?;
+    }
+
+    if let Some(window) = &window {
+        window.to_grid_frame(&mut peaks);
+    }
+    let info = RunInfo {
+        input: filename,
+        rows: input_size.0,
+        cols: input_size.1,
+        max_peaks: options.get_max_peaks(),
+        min_prominence,
+        georef: input_georef,
+        window,
+    };
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    output::write_peaks(&mut out, format, &peaks, &info)?;
+    out.flush()?;
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
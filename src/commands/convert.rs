@@ -0,0 +1,52 @@
+use std::process::ExitCode;
+
+use topographic_prominence::{write_grid, Monitor, Phase, Registry, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence convert <input> <output> [options]
+
+Convert a grid between the supported formats, chosen by extension:
+.csv, .bin (16-bit, with a .pat header sidecar) and .asc (ESRI ASCII).
+The georeference and no-data marker are carried over where the output
+format can hold them.
+
+  --raw    keep values as stored instead of clamping .bin input to sea level";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut paths = Vec::new();
+    let mut raw = false;
+
+    while let Some(arg) = args.next() {
+        if arg == "--raw" {
+            raw = true;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || paths.len() == 2 {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.len() != 2 {
+        args.usage_error("Expected an input and an output file");
+    }
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    reporting.timings.begin(Phase::Read.name());
+    let mut source = Registry::default().open(&paths[0])?;
+    source.set_raw(raw);
+    let grid = source.read_all_with(&monitor)?;
+    reporting.finish_progress();
+
+    reporting.timings.begin("output");
+    write_grid(&paths[1], &grid, source.georef().as_ref(), source.no_data())?;
+    topographic_prominence::info!("Wrote {}x{} grid to '{}'", grid.rows, grid.cols, paths[1]);
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
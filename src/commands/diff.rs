@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use topographic_prominence::diff::{self, diff_peaks, read_results, DiffOptions};
+use topographic_prominence::{Error, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence diff <reference> <candidate> [options]
+
+Compare two result files (text table, CSV or JSON) peak by peak. Peaks are
+matched by summit location; the report lists changed, missing and extra
+peaks. Exits with status 1 when the results diverge beyond the thresholds,
+so it can serve as a regression gate.
+
+  --format <name>             report as text, csv or json (default text)
+  --tolerance <cells>         summit offset still matched as the same peak (default 0)
+  --top <n>                   compare only the first n peaks of each file
+  --max-prominence-diff <m>   accepted prominence difference (default 0)
+  --max-col-distance <cells>  accepted key col offset (default 0)
+  --max-missing <n>           accepted number of missing peaks (default 0)
+  --max-extra <n>             accepted number of extra peaks (default 0)";
+
+#[derive(Clone, Copy)]
+enum ReportFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut paths = Vec::new();
+    let mut options = DiffOptions::default();
+    let mut format = ReportFormat::Text;
+    let mut top = usize::MAX;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--format")? {
+            format = match value.to_ascii_lowercase().as_str() {
+                "text" | "txt" => ReportFormat::Text,
+                "csv" => ReportFormat::Csv,
+                "json" => ReportFormat::Json,
+                _ => {
+                    return Err(Error::UnsupportedFormat(format!(
+                        "report format '{}'",
+                        value
+                    )))
+                }
+            };
+        } else if let Some(value) = args.parsed(&arg, "--tolerance")? {
+            options.tolerance = value;
+        } else if let Some(value) = args.parsed(&arg, "--top")? {
+            top = value;
+        } else if let Some(value) = args.parsed(&arg, "--max-prominence-diff")? {
+            options.max_prominence_diff = value;
+        } else if let Some(value) = args.parsed(&arg, "--max-col-distance")? {
+            options.max_col_distance = value;
+        } else if let Some(value) = args.parsed(&arg, "--max-missing")? {
+            options.max_missing = value;
+        } else if let Some(value) = args.parsed(&arg, "--max-extra")? {
+            options.max_extra = value;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || paths.len() == 2 {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.len() != 2 {
+        args.usage_error("Expected a reference and a candidate result file");
+    }
+
+    reporting.timings.begin("read");
+    let mut reference = read_results(&paths[0])?;
+    let mut candidate = read_results(&paths[1])?;
+    reference.truncate(top);
+    candidate.truncate(top);
+
+    reporting.timings.begin("output");
+    let diff = diff_peaks(&reference, &candidate, &options);
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    match format {
+        ReportFormat::Text => diff::write_diff_text(&mut out, &diff, &options)?,
+        ReportFormat::Csv => diff::write_diff_csv(&mut out, &diff, &options)?,
+        ReportFormat::Json => diff::write_diff_json(&mut out, &diff, &options)?,
+    }
+    out.flush()?;
+
+    reporting.finish()?;
+    Ok(if diff.diverges(&options) {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,74 @@
+use std::process::ExitCode;
+
+use topographic_prominence::synth::{self, Terrain, TerrainOptions};
+use topographic_prominence::{write_grid, Error, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence generate <output> [options]
+
+Generate synthetic terrain and write it as .csv, .bin or .asc by extension.
+The same seed always gives the same grid.
+
+  --terrain <kind>      diamond-square, noise, staircase, plateau,
+                        checkerboard or spike (default diamond-square)
+  --size <rows>x<cols>  grid size (default 512x512)
+  --seed <n>            random seed (default 1)
+  --relief <m>          highest elevation (default 3000)
+  --roughness <r>       amplitude kept per octave or subdivision, between
+                        0 and 1 (default 0.55)";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut output = None;
+    let mut options = TerrainOptions::default();
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--terrain")? {
+            options.terrain = Terrain::from_name(&value)
+                .ok_or_else(|| Error::InvalidParameter(format!("--terrain '{}'", value)))?;
+        } else if let Some(value) = args.value(&arg, "--size")? {
+            (options.rows, options.cols) = parse_size(&value)?;
+        } else if let Some(value) = args.parsed(&arg, "--seed")? {
+            options.seed = value;
+        } else if let Some(value) = args.parsed(&arg, "--relief")? {
+            options.relief = value;
+        } else if let Some(value) = args.parsed(&arg, "--roughness")? {
+            options.roughness = value;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || output.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            output = Some(arg);
+        }
+    }
+    let output = &output.unwrap_or_else(|| args.usage_error("Missing output file"));
+
+    reporting.timings.begin("generate");
+    let grid = synth::generate(&options)?;
+
+    reporting.timings.begin("output");
+    write_grid(output, &grid, None, None)?;
+    topographic_prominence::info!(
+        "Wrote {}x{} {} terrain (seed {}) to '{}'",
+        grid.rows,
+        grid.cols,
+        options.terrain.name(),
+        options.seed,
+        output
+    );
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// "<rows>x<cols>", or a single number for a square grid
+fn parse_size(value: &str) -> Result<(usize, usize)> {
+    let (rows, cols) = value.split_once(['x', 'X']).unwrap_or((value, value));
+    Ok((
+        super::parse_value("--size", rows)?,
+        super::parse_value("--size", cols)?,
+    ))
+}
@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use topographic_prominence::divide_tree::build_divide_tree;
+use topographic_prominence::{Monitor, Phase, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence index <grid> <index>
+
+Compute the divide tree of a grid, every peak with its key col and the
+higher summit it connects to, and save it to an index file. 'query' answers
+from the index without reading the grid again.";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || paths.len() == 2 {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            paths.push(arg);
+        }
+    }
+    let [input, index] = &paths[..] else {
+        args.usage_error("Expected a grid and an index file");
+    };
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    reporting.timings.begin(Phase::Read.name());
+    let tree = build_divide_tree(input, &monitor)?;
+    reporting.finish_progress();
+
+    reporting.timings.begin("output");
+    tree.write(index)?;
+    writeln!(
+        io::stdout(),
+        "{} peaks of {}x{} grid indexed in {}",
+        tree.nodes.len(),
+        tree.rows,
+        tree.cols,
+        index
+    )?;
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
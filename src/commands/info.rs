@@ -0,0 +1,148 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use topographic_prominence::{Monitor, Phase, Registry, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence info <grid> [options]
+
+Describe a grid: format, dimensions, georeference, elevation range,
+no-data cells and an elevation histogram. Values are shown as stored,
+before the binary reader's clamping to sea level.
+
+  --bins <n>    histogram bins (default 10)";
+
+// Width of the longest histogram bar in characters
+const BAR_WIDTH: usize = 40;
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut filename = None;
+    let mut bins: usize = 10;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.parsed(&arg, "--bins")? {
+            bins = value;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || filename.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            filename = Some(arg);
+        }
+    }
+    let filename = &filename.unwrap_or_else(|| args.usage_error("Missing input grid"));
+    let bins = bins.max(1);
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    reporting.timings.begin(Phase::Read.name());
+    let registry = Registry::default();
+    let format = registry.detect(filename)?;
+    let mut source = format.open(filename)?;
+    source.set_raw(true);
+    let grid = source.read_all_with(&monitor)?;
+    let no_data = source.no_data();
+    reporting.finish_progress();
+
+    reporting.timings.begin("output");
+    let values: Vec<i32> = grid
+        .data
+        .iter()
+        .copied()
+        .filter(|&v| Some(v) != no_data)
+        .collect();
+    let no_data_count = grid.len() - values.len();
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    writeln!(out, "File:       {}", filename)?;
+    writeln!(out, "Format:     {}", format.name())?;
+    writeln!(
+        out,
+        "Size:       {} rows x {} cols ({} cells)",
+        grid.rows,
+        grid.cols,
+        grid.len()
+    )?;
+    match source.georef() {
+        Some(georef) => {
+            let (east, south) = (
+                georef.west + grid.cols as f64 * georef.cell_width,
+                georef.north - grid.rows as f64 * georef.cell_height,
+            );
+            writeln!(
+                out,
+                "Georef:     west {}, north {}, east {}, south {}",
+                georef.west, georef.north, east, south
+            )?;
+            writeln!(
+                out,
+                "Cell size:  {}\" x {}\"",
+                georef.cell_width * 3600.0,
+                georef.cell_height * 3600.0
+            )?;
+        }
+        None => writeln!(out, "Georef:     none")?,
+    }
+    match no_data {
+        Some(marker) => writeln!(
+            out,
+            "No data:    {} cells (value {})",
+            no_data_count, marker
+        )?,
+        None => writeln!(out, "No data:    no marker")?,
+    }
+
+    let (min, max) = match (values.iter().min(), values.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => {
+            writeln!(out, "Elevation:  no valid cells")?;
+            out.flush()?;
+            reporting.finish()?;
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+    let mean = values.iter().map(|&v| v as f64).sum::<f64>() / values.len() as f64;
+    writeln!(
+        out,
+        "Elevation:  min {}, max {}, mean {:.1}",
+        min, max, mean
+    )?;
+    writeln!(
+        out,
+        "Below zero: {} cells",
+        values.iter().filter(|&&v| v < 0).count()
+    )?;
+
+    // Equal-width bins over [min, max], widened first as the span of i32
+    // values needs 33 bits and its products with bin numbers more than 64
+    let span = max as i128 - min as i128 + 1;
+    let bins = bins.min(span as usize);
+    let mut counts = vec![0usize; bins];
+    for &v in &values {
+        counts[((v as i128 - min as i128) * bins as i128 / span) as usize] += 1;
+    }
+    let largest = counts.iter().copied().max().unwrap_or(0).max(1);
+    writeln!(out)?;
+    writeln!(out, "Histogram:")?;
+    for (i, &count) in counts.iter().enumerate() {
+        let low = min as i128 + span * i as i128 / bins as i128;
+        let high = min as i128 + span * (i as i128 + 1) / bins as i128 - 1;
+        writeln!(
+            out,
+            "{:>7} .. {:>7} {:>10}  {}",
+            low,
+            high,
+            count,
+            "#".repeat((count * BAR_WIDTH).div_ceil(largest))
+        )?;
+    }
+    out.flush()?;
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use topographic_prominence::divide_tree::{read_divide_tree, DivideTree};
+use topographic_prominence::output::{self, OutputFormat, RunInfo};
+use topographic_prominence::{Error, ProminenceOptions, Result};
+
+use super::{Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence query <index> [options]
+
+Answer from an index written by 'index', without the grid. Peaks are listed
+exactly as 'compute' lists them for the same options.
+
+  --format <name>         text, csv, json, geojson, kml or gpx (default text)
+  --max-peaks <n|all>     number of peaks reported (default 100)
+  --min-prominence <m>    drop peaks with less prominence
+  --peak <row>,<col>      the peak at this summit with its key col and parent
+  --verify <grid>         fail unless the index was built from this file";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut filename = None;
+    let mut format = OutputFormat::Text;
+    let mut options = ProminenceOptions::new();
+    let mut min_prominence = 0;
+    let mut summit = None;
+    let mut verify = None;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--format")? {
+            format = OutputFormat::from_name(&value)
+                .ok_or_else(|| Error::UnsupportedFormat(format!("output format '{}'", value)))?;
+        } else if let Some(value) = args.value(&arg, "--max-peaks")? {
+            options = if value == "all" {
+                options.unlimited()
+            } else {
+                options.max_peaks(super::parse_value("--max-peaks", &value)?)
+            };
+        } else if let Some(value) = args.parsed(&arg, "--min-prominence")? {
+            min_prominence = value;
+        } else if let Some(value) = args.value(&arg, "--peak")? {
+            summit = Some(super::parse_cell("--peak", &value)?);
+        } else if let Some(value) = args.value(&arg, "--verify")? {
+            verify = Some(value);
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || filename.is_some() {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            filename = Some(arg);
+        }
+    }
+    let filename = &filename.unwrap_or_else(|| args.usage_error("Missing index file"));
+
+    reporting.timings.begin("read index");
+    let tree = read_divide_tree(filename)?;
+    if let Some(grid) = &verify {
+        if !tree.matches_input(grid)? {
+            return Err(Error::format(
+                filename,
+                format!("not built from '{}' as it is now", grid),
+            ));
+        }
+    }
+
+    reporting.timings.begin("output");
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    match summit {
+        Some((row, col)) => write_peak(&mut out, &tree, row, col)?,
+        None => {
+            let options = options.min_prominence(min_prominence);
+            let peaks = tree.peaks(&options);
+            let info = RunInfo {
+                input: &tree.input,
+                rows: tree.rows,
+                cols: tree.cols,
+                max_peaks: options.get_max_peaks(),
+                min_prominence,
+                georef: tree.georef,
+                window: None,
+            };
+            output::write_peaks(&mut out, format, &peaks, &info)?;
+        }
+    }
+    out.flush()?;
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn write_peak<W: Write>(out: &mut W, tree: &DivideTree, row: usize, col: usize) -> Result<()> {
+    let node = tree
+        .peak_at(row, col)
+        .ok_or_else(|| Error::InvalidParameter(format!("no peak at row {}, col {}", row, col)))?;
+    let peak = &node.peak;
+    let place = |row: usize, col: usize| match &tree.georef {
+        Some(georef) => {
+            let (lon, lat) = georef.cell_center(row, col);
+            format!("row {}, col {} ({:.5}, {:.5})", row, col, lon, lat)
+        }
+        None => format!("row {}, col {}", row, col),
+    };
+    writeln!(
+        out,
+        "Summit:     {}, {} m",
+        place(row, col),
+        peak.peak_elevation
+    )?;
+    writeln!(out, "Prominence: {} m", peak.prominence)?;
+    match (peak.col_x, peak.col_y, peak.col_elevation) {
+        (Some(x), Some(y), Some(elevation)) => {
+            writeln!(out, "Key col:    {}, {} m", place(x, y), elevation)?
+        }
+        _ => writeln!(out, "Key col:    none")?,
+    }
+    match node.parent {
+        Some((x, y)) => match tree.peak_at(x, y) {
+            Some(parent) => writeln!(
+                out,
+                "Parent:     {}, {} m",
+                place(x, y),
+                parent.peak.peak_elevation
+            )?,
+            None => writeln!(out, "Parent:     {}", place(x, y))?,
+        },
+        None => writeln!(out, "Parent:     none")?,
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::process::ExitCode;
+
+use topographic_prominence::render::{self, RenderOptions, RenderStyle};
+use topographic_prominence::{compute_prominence_with, Error, Monitor, ProminenceOptions, Result};
+
+use super::{read_input, Args, Reporting};
+
+pub const USAGE: &str = "\
+Usage: topographic_prominence render <grid> <map.png|map.svg> [options]
+
+Draw the grid as a shaded relief map with its most prominent peaks and
+their key cols marked.
+
+  --style <style>        hillshade or tint (default hillshade)
+  --size <px>            longest image side (default 1024)
+  --top <n>              peaks marked on the map (default 20)
+  --min-prominence <m>   only mark peaks with at least this prominence";
+
+pub fn run(args: Vec<String>) -> Result<ExitCode> {
+    let mut args = Args::new(args, USAGE);
+    let mut reporting = Reporting::new();
+    let mut paths = Vec::new();
+    let mut options = RenderOptions::default();
+    let mut min_prominence = 0;
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = args.value(&arg, "--style")? {
+            options.style = RenderStyle::from_name(&value)
+                .ok_or_else(|| Error::InvalidParameter(format!("--style '{}'", value)))?;
+        } else if let Some(value) = args.parsed(&arg, "--size")? {
+            options.size = value;
+        } else if let Some(value) = args.parsed(&arg, "--top")? {
+            options.top = value;
+        } else if let Some(value) = args.parsed(&arg, "--min-prominence")? {
+            min_prominence = value;
+        } else if reporting.parse(&arg) {
+        } else if arg.starts_with('-') || paths.len() == 2 {
+            args.usage_error(&format!("Unexpected argument '{}'", arg));
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.len() != 2 {
+        args.usage_error("Expected an input grid and an output image");
+    }
+
+    let update = |phase, processed, total| reporting.update(phase, processed, total);
+    let monitor = Monitor::new().progress(&update);
+
+    let (grid, georef) = read_input(&paths[0], &reporting, &monitor)?;
+    let prominence_options = ProminenceOptions::new()
+        .max_peaks(options.top)
+        .min_prominence(min_prominence);
+    let peaks = compute_prominence_with(&grid, &prominence_options, &monitor)?.peaks;
+    reporting.finish_progress();
+
+    reporting.timings.begin("output");
+    render::render_map(
+        &paths[1],
+        &grid.to_grid(),
+        &peaks,
+        georef.as_ref(),
+        &options,
+    )?;
+
+    reporting.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
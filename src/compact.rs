@@ -0,0 +1,133 @@
+use crate::error::Result;
+use crate::grid::{check_size, Elevations, Grid};
+use crate::progress::Monitor;
+use crate::source::Registry;
+
+// Elevation samples at their native width
+#[derive(Clone, Debug, PartialEq)]
+pub enum Samples {
+    I16(Vec<i16>),
+    U16(Vec<u16>),
+    // Integer elevations beyond 16 bits
+    I32(Vec<i32>),
+    // Fractional elevations; reported rounded to whole metres
+    F32(Vec<f32>),
+}
+
+impl Samples {
+    pub fn len(&self) -> usize {
+        match self {
+            Samples::I16(values) => values.len(),
+            Samples::U16(values) => values.len(),
+            Samples::I32(values) => values.len(),
+            Samples::F32(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Bytes per sample
+    pub fn width(&self) -> usize {
+        match self {
+            Samples::I16(_) | Samples::U16(_) => 2,
+            Samples::I32(_) | Samples::F32(_) => 4,
+        }
+    }
+}
+
+// Row-major grid of native-width samples, half the size of a `Grid` for
+// 16-bit DEMs such as GTOPO30
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactGrid {
+    pub rows: usize,
+    pub cols: usize,
+    pub samples: Samples,
+}
+
+impl CompactGrid {
+    pub fn new(rows: usize, cols: usize, samples: Samples) -> Result<CompactGrid> {
+        check_size(rows, cols, samples.len())?;
+        Ok(CompactGrid {
+            rows,
+            cols,
+            samples,
+        })
+    }
+
+    // The narrowest samples holding every value of `grid` exactly
+    pub fn from_grid(grid: &Grid) -> CompactGrid {
+        let min = grid.data.iter().copied().min().unwrap_or(0);
+        let max = grid.data.iter().copied().max().unwrap_or(0);
+        let samples = if min >= i16::MIN as i32 && max <= i16::MAX as i32 {
+            Samples::I16(grid.data.iter().map(|&v| v as i16).collect())
+        } else if min >= 0 && max <= u16::MAX as i32 {
+            Samples::U16(grid.data.iter().map(|&v| v as u16).collect())
+        } else {
+            Samples::I32(grid.data.clone())
+        };
+        CompactGrid {
+            rows: grid.rows,
+            cols: grid.cols,
+            samples,
+        }
+    }
+
+    // Widen to a `Grid`, rounding fractional samples
+    pub fn to_grid(&self) -> Grid {
+        let data = (0..self.len()).map(|i| self.metres(i)).collect();
+        Grid {
+            rows: self.rows,
+            cols: self.cols,
+            data,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+impl Elevations for CompactGrid {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn metres(&self, index: usize) -> i32 {
+        match &self.samples {
+            Samples::I16(values) => values[index] as i32,
+            Samples::U16(values) => values[index] as i32,
+            Samples::I32(values) => values[index],
+            Samples::F32(values) => values[index].round() as i32,
+        }
+    }
+
+    fn key(&self, index: usize) -> u32 {
+        match &self.samples {
+            Samples::I16(values) => (values[index] as u16 ^ 0x8000) as u32,
+            Samples::U16(values) => values[index] as u32,
+            Samples::I32(values) => values[index] as u32 ^ 0x8000_0000,
+            Samples::F32(values) => {
+                // IEEE bits flipped so that unsigned order is numeric order
+                let bits = values[index].to_bits();
+                if bits & 0x8000_0000 != 0 {
+                    !bits
+                } else {
+                    bits | 0x8000_0000
+                }
+            }
+        }
+    }
+}
+
+// Read a grid with the built-in readers at its native sample width
+pub fn read_compact_grid(filename: &str) -> Result<CompactGrid> {
+    Registry::default()
+        .open(filename)?
+        .read_compact_with(&Monitor::new())
+}
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::error::{Error, Result};
+use crate::json::{self, Json};
+use crate::prominence::Peak;
+
+// Matching tolerance and the thresholds beyond which two results diverge
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiffOptions {
+    // Largest summit offset, in cells along either axis, still counted as the same peak
+    pub tolerance: usize,
+    // Largest acceptable prominence difference of a matched peak
+    pub max_prominence_diff: i32,
+    // Largest acceptable key col offset, in cells along either axis
+    pub max_col_distance: usize,
+    pub max_missing: usize,
+    pub max_extra: usize,
+}
+
+// A reference peak and the candidate peak matched to it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeakMatch {
+    pub reference: Peak,
+    pub candidate: Peak,
+    // Summit offset in cells (Chebyshev distance)
+    pub distance: usize,
+    // Candidate minus reference prominence
+    pub prominence_diff: i32,
+    // Key col offset in cells; None when only one of the two has a col
+    pub col_distance: Option<usize>,
+}
+
+impl PeakMatch {
+    pub fn is_identical(&self) -> bool {
+        self.reference == self.candidate
+    }
+
+    // Whether this match exceeds the prominence or col thresholds
+    pub fn diverges(&self, options: &DiffOptions) -> bool {
+        let col_moved = match self.col_distance {
+            Some(distance) => distance > options.max_col_distance,
+            None => true,
+        };
+        self.prominence_diff.abs() > options.max_prominence_diff || col_moved
+    }
+}
+
+// Differences between a reference peak list and a candidate one
+#[derive(Debug, Default, PartialEq)]
+pub struct PeakDiff {
+    pub matches: Vec<PeakMatch>,
+    // Reference peaks with no counterpart in the candidate
+    pub missing: Vec<Peak>,
+    // Candidate peaks with no counterpart in the reference
+    pub extra: Vec<Peak>,
+}
+
+impl PeakDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.matches.iter().all(PeakMatch::is_identical)
+    }
+
+    // Matches that differ in any field
+    pub fn changed(&self) -> impl Iterator<Item = &PeakMatch> {
+        self.matches.iter().filter(|m| !m.is_identical())
+    }
+
+    // Whether the results differ beyond the thresholds of `options`
+    pub fn diverges(&self, options: &DiffOptions) -> bool {
+        self.missing.len() > options.max_missing
+            || self.extra.len() > options.max_extra
+            || self.matches.iter().any(|m| m.diverges(options))
+    }
+}
+
+// Read the peaks of a result file written as a text table, CSV or JSON
+pub fn read_results(path: &str) -> Result<Vec<Peak>> {
+    let content = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('{') {
+        parse_json(path, &content)
+    } else if trimmed.starts_with("prominence,") {
+        parse_csv(path, &content)
+    } else {
+        parse_text(path, &content)
+    }
+}
+
+// Rows of the text table; the title, header and rule lines are skipped
+fn parse_text(path: &str, content: &str) -> Result<Vec<Peak>> {
+    let mut peaks = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if !(7..=8).contains(&fields.len()) || fields[0].parse::<i32>().is_err() {
+            continue;
+        }
+        peaks.push(parse_fields(path, line_number, &fields, "NA")?);
+    }
+    Ok(peaks)
+}
+
+// CSV with the header written by `output`; empty fields for missing cols
+fn parse_csv(path: &str, content: &str) -> Result<Vec<Peak>> {
+    let mut peaks = Vec::new();
+    for (line_number, line) in content.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if !(7..=8).contains(&fields.len()) {
+            return Err(Error::format(
+                path,
+                format!(
+                    "line {} has {} fields, expected 7 or 8",
+                    line_number + 1,
+                    fields.len()
+                ),
+            ));
+        }
+        peaks.push(parse_fields(path, line_number, &fields, "")?);
+    }
+    Ok(peaks)
+}
+
+// prominence, row, col, elevation, col row, col col, col elevation and,
+// for cropped runs, the edge flag
+fn parse_fields(path: &str, line_number: usize, fields: &[&str], missing: &str) -> Result<Peak> {
+    let number = |column: usize| {
+        fields[column].parse::<i64>().map_err(|_| Error::Parse {
+            path: path.to_string(),
+            line: line_number + 1,
+            column: column + 1,
+            message: format!("invalid value '{}'", fields[column]),
+        })
+    };
+    let cell = |column: usize| {
+        usize::try_from(number(column)?).map_err(|_| {
+            Error::format(
+                path,
+                format!(
+                    "line {} column {}: negative cell index '{}'",
+                    line_number + 1,
+                    column + 1,
+                    fields[column]
+                ),
+            )
+        })
+    };
+    let optional = |column: usize| fields[column] != missing;
+    Ok(Peak {
+        prominence: number(0)? as i32,
+        peak_x: cell(1)?,
+        peak_y: cell(2)?,
+        peak_elevation: number(3)? as i32,
+        col_x: optional(4).then(|| cell(4)).transpose()?,
+        col_y: optional(5).then(|| cell(5)).transpose()?,
+        col_elevation: optional(6)
+            .then(|| number(6))
+            .transpose()?
+            .map(|v| v as i32),
+        edge_affected: fields
+            .get(7)
+            .is_some_and(|&flag| flag == "yes" || flag == "true"),
+    })
+}
+
+// The `peaks` array of a JSON result document
+fn parse_json(path: &str, content: &str) -> Result<Vec<Peak>> {
+    let document = json::parse(path, content)?;
+    let peaks = document
+        .get("peaks")
+        .and_then(Json::as_array)
+        .ok_or_else(|| Error::format(path, "no \"peaks\" array"))?;
+    peaks
+        .iter()
+        .enumerate()
+        .map(|(i, peak)| {
+            let field = |key: &str| peak.get(key).and_then(Json::as_f64);
+            let required = |key: &str| {
+                field(key).ok_or_else(|| {
+                    Error::format(path, format!("peak {} has no numeric \"{}\"", i, key))
+                })
+            };
+            let cell = |key: &str, value: f64| {
+                if value < 0.0 {
+                    return Err(Error::format(
+                        path,
+                        format!("peak {} has a negative \"{}\"", i, key),
+                    ));
+                }
+                Ok(value as usize)
+            };
+            let optional_cell = |key: &str| field(key).map(|v| cell(key, v)).transpose();
+            Ok(Peak {
+                prominence: required("prominence")? as i32,
+                peak_x: cell("row", required("row")?)?,
+                peak_y: cell("col", required("col")?)?,
+                peak_elevation: required("elevation")? as i32,
+                col_x: optional_cell("col_row")?,
+                col_y: optional_cell("col_col")?,
+                col_elevation: field("col_elevation").map(|v| v as i32),
+                edge_affected: peak
+                    .get("edge_affected")
+                    .and_then(Json::as_bool)
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+fn chebyshev(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+// Match each reference peak, in order, to the nearest unmatched candidate
+// summit within `options.tolerance` cells, preferring equal prominence on ties
+pub fn diff_peaks(reference: &[Peak], candidate: &[Peak], options: &DiffOptions) -> PeakDiff {
+    let tolerance = options.tolerance;
+    // Candidates in square buckets one tolerance wide, so that every summit
+    // within tolerance of a cell lies in its bucket or the eight around it
+    let size = tolerance.saturating_add(1);
+    let bucket = |row: usize, col: usize| (row / size, col / size);
+    let mut buckets: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, peak) in candidate.iter().enumerate() {
+        buckets
+            .entry(bucket(peak.peak_x, peak.peak_y))
+            .or_default()
+            .push(i);
+    }
+    let mut taken = vec![false; candidate.len()];
+
+    let mut diff = PeakDiff::default();
+    for peak in reference {
+        let (row, col) = (peak.peak_x, peak.peak_y);
+        let mut best: Option<(usize, i32, usize)> = None;
+        let (bucket_row, bucket_col) = bucket(row, col);
+        for r in bucket_row.saturating_sub(1)..=bucket_row.saturating_add(1) {
+            for c in bucket_col.saturating_sub(1)..=bucket_col.saturating_add(1) {
+                for &i in buckets.get(&(r, c)).into_iter().flatten() {
+                    let other = &candidate[i];
+                    let distance = chebyshev((row, col), (other.peak_x, other.peak_y));
+                    if taken[i] || distance > tolerance {
+                        continue;
+                    }
+                    let key = (distance, (other.prominence - peak.prominence).abs(), i);
+                    if best.is_none_or(|b| key < b) {
+                        best = Some(key);
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((distance, _, i)) => {
+                taken[i] = true;
+                let other = candidate[i];
+                let col_distance = match (peak.col_x.zip(peak.col_y), other.col_x.zip(other.col_y))
+                {
+                    (Some(a), Some(b)) => Some(chebyshev(a, b)),
+                    (None, None) => Some(0),
+                    _ => None,
+                };
+                diff.matches.push(PeakMatch {
+                    reference: *peak,
+                    candidate: other,
+                    distance,
+                    prominence_diff: other.prominence - peak.prominence,
+                    col_distance,
+                });
+            }
+            None => diff.missing.push(*peak),
+        }
+    }
+    diff.extra = candidate
+        .iter()
+        .zip(&taken)
+        .filter(|(_, &taken)| !taken)
+        .map(|(peak, _)| *peak)
+        .collect();
+    diff
+}
+
+fn col_text(peak: &Peak) -> String {
+    match (peak.col_x, peak.col_y) {
+        (Some(x), Some(y)) => format!("({}, {})", x, y),
+        _ => "none".to_string(),
+    }
+}
+
+pub fn write_diff_text<W: Write>(
+    out: &mut W,
+    diff: &PeakDiff,
+    options: &DiffOptions,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} matched, {} changed, {} missing, {} extra",
+        diff.matches.len(),
+        diff.changed().count(),
+        diff.missing.len(),
+        diff.extra.len()
+    )?;
+    for m in diff.changed() {
+        let (r, c) = (m.reference, m.candidate);
+        let mut parts = Vec::new();
+        if m.distance > 0 {
+            parts.push(format!("summit moved to ({}, {})", c.peak_x, c.peak_y));
+        }
+        if m.prominence_diff != 0 {
+            parts.push(format!(
+                "prominence {} -> {} ({:+})",
+                r.prominence, c.prominence, m.prominence_diff
+            ));
+        }
+        if m.col_distance != Some(0) {
+            parts.push(format!("col {} -> {}", col_text(&r), col_text(&c)));
+        }
+        if r.peak_elevation != c.peak_elevation {
+            parts.push(format!(
+                "elevation {} -> {}",
+                r.peak_elevation, c.peak_elevation
+            ));
+        }
+        if parts.is_empty() {
+            parts.push(format!(
+                "col elevation {:?} -> {:?}",
+                r.col_elevation, c.col_elevation
+            ));
+        }
+        writeln!(
+            out,
+            "{} ({}, {}): {}",
+            if m.diverges(options) {
+                "changed"
+            } else {
+                "within "
+            },
+            r.peak_x,
+            r.peak_y,
+            parts.join(", ")
+        )?;
+    }
+    for peak in &diff.missing {
+        writeln!(
+            out,
+            "missing ({}, {}): prominence {}",
+            peak.peak_x, peak.peak_y, peak.prominence
+        )?;
+    }
+    for peak in &diff.extra {
+        writeln!(
+            out,
+            "extra   ({}, {}): prominence {}",
+            peak.peak_x, peak.peak_y, peak.prominence
+        )?;
+    }
+    Ok(())
+}
+
+// One row per changed, missing or extra peak
+pub fn write_diff_csv<W: Write>(
+    out: &mut W,
+    diff: &PeakDiff,
+    options: &DiffOptions,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "kind,row,col,other_row,other_col,prominence,other_prominence,prominence_diff,col_row,col_col,other_col_row,other_col_col,col_distance,diverges"
+    )?;
+    let optional = |v: Option<usize>| v.map_or(String::new(), |v| v.to_string());
+    for m in diff.changed() {
+        let (r, c) = (m.reference, m.candidate);
+        writeln!(
+            out,
+            "changed,{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            r.peak_x,
+            r.peak_y,
+            c.peak_x,
+            c.peak_y,
+            r.prominence,
+            c.prominence,
+            m.prominence_diff,
+            optional(r.col_x),
+            optional(r.col_y),
+            optional(c.col_x),
+            optional(c.col_y),
+            optional(m.col_distance),
+            m.diverges(options)
+        )?;
+    }
+    for (kind, peaks) in [("missing", &diff.missing), ("extra", &diff.extra)] {
+        for p in peaks {
+            writeln!(
+                out,
+                "{},{},{},,,{},,,{},{},,,,true",
+                kind,
+                p.peak_x,
+                p.peak_y,
+                p.prominence,
+                optional(p.col_x),
+                optional(p.col_y)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_diff_json<W: Write>(
+    out: &mut W,
+    diff: &PeakDiff,
+    options: &DiffOptions,
+) -> io::Result<()> {
+    let peak_json = |p: &Peak| {
+        format!(
+            "{{\"row\": {}, \"col\": {}, \"prominence\": {}, \"elevation\": {}, \"col_row\": {}, \"col_col\": {}}}",
+            p.peak_x,
+            p.peak_y,
+            p.prominence,
+            p.peak_elevation,
+            p.col_x.map_or("null".to_string(), |v| v.to_string()),
+            p.col_y.map_or("null".to_string(), |v| v.to_string())
+        )
+    };
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n    {}\n  ]", items.join(",\n    "))
+        }
+    };
+
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"matched\": {},", diff.matches.len())?;
+    writeln!(out, "  \"diverges\": {},", diff.diverges(options))?;
+    let changed = diff
+        .changed()
+        .map(|m| {
+            format!(
+                "{{\"reference\": {}, \"candidate\": {}, \"distance\": {}, \"prominence_diff\": {}, \"col_distance\": {}, \"diverges\": {}}}",
+                peak_json(&m.reference),
+                peak_json(&m.candidate),
+                m.distance,
+                m.prominence_diff,
+                m.col_distance.map_or("null".to_string(), |v| v.to_string()),
+                m.diverges(options)
+            )
+        })
+        .collect();
+    writeln!(out, "  \"changed\": {},", list(changed))?;
+    writeln!(
+        out,
+        "  \"missing\": {},",
+        list(diff.missing.iter().map(peak_json).collect())
+    )?;
+    writeln!(
+        out,
+        "  \"extra\": {}",
+        list(diff.extra.iter().map(peak_json).collect())
+    )?;
+    writeln!(out, "}}")
+}
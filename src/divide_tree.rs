@@ -0,0 +1,289 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+
+use crate::error::{Error, Result};
+use crate::georef::Georef;
+use crate::grid::Elevations;
+use crate::progress::Monitor;
+use crate::prominence::{self, top_peaks, Peak, ProminenceOptions};
+use crate::source::Registry;
+
+// Start of every index file
+const MAGIC: &[u8; 8] = b"TPDTREE\0";
+const VERSION: u32 = 1;
+
+// Bytes per peak on disk: summit, elevation, col, col elevation, parent
+const NODE_BYTES: usize = 20;
+
+// Stored in place of a missing col or parent
+const NONE: u32 = u32::MAX;
+
+// A peak of the divide tree with the summit it hangs from
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TreeNode {
+    pub peak: Peak,
+    // (row, col) of the highest cell of the higher ground met at the key
+    // col. This is a flat summit rather than a peak when that ground tops
+    // out in a plateau. None for the highest peak.
+    pub parent: Option<(usize, usize)>,
+}
+
+// Every peak of a grid with its key col and parent, in the order the sweep
+// found them, so that peak lists for any threshold come out exactly as
+// `compute_prominence` would give them, without the grid. Saved with
+// `write` and loaded with `read_divide_tree`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DivideTree {
+    pub rows: usize,
+    pub cols: usize,
+    pub georef: Option<Georef>,
+    // File the tree was built from, its size and FNV-1a hash
+    pub input: String,
+    pub input_size: u64,
+    pub checksum: u64,
+    pub nodes: Vec<TreeNode>,
+}
+
+impl DivideTree {
+    // Tree of `grid`, without input details
+    pub fn build<G: Elevations + ?Sized>(grid: &G, monitor: &Monitor) -> Result<DivideTree> {
+        let (rows, cols) = grid.dimensions();
+        let options = ProminenceOptions::new()
+            .unlimited()
+            .min_prominence(i32::MIN);
+        let mut nodes = Vec::new();
+        prominence::sweep(grid, &options, monitor, |peak, parent| {
+            nodes.push(TreeNode {
+                peak,
+                parent: parent.map(|cell| (cell / cols, cell % cols)),
+            });
+            ControlFlow::Continue(())
+        })?;
+        Ok(DivideTree {
+            rows,
+            cols,
+            georef: None,
+            input: String::new(),
+            input_size: 0,
+            checksum: 0,
+            nodes,
+        })
+    }
+
+    // Peaks passing `options`, ordered and cut as `compute_prominence` does
+    pub fn peaks(&self, options: &ProminenceOptions) -> Vec<Peak> {
+        let min_prominence = options.get_min_prominence();
+        let found = self
+            .nodes
+            .iter()
+            .map(|node| node.peak)
+            .filter(|peak| peak.prominence >= min_prominence);
+        top_peaks(found, options.get_max_peaks())
+    }
+
+    // The peak whose summit is at (`row`, `col`)
+    pub fn peak_at(&self, row: usize, col: usize) -> Option<&TreeNode> {
+        self.nodes
+            .iter()
+            .find(|node| (node.peak.peak_x, node.peak.peak_y) == (row, col))
+    }
+
+    // Whether the file at `path` is the one the tree was built from
+    pub fn matches_input(&self, path: &str) -> Result<bool> {
+        Ok(file_checksum(path)? == (self.input_size, self.checksum))
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        let cell = |position: Option<(usize, usize)>| {
+            position.map_or(NONE, |(row, col)| (row * self.cols + col) as u32)
+        };
+        let mut bytes = Vec::with_capacity(128 + self.input.len() + self.nodes.len() * NODE_BYTES);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.rows as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.cols as u64).to_le_bytes());
+        bytes.push(self.georef.is_some() as u8);
+        let georef = self.georef.unwrap_or(Georef::GRID);
+        for value in [
+            georef.west,
+            georef.north,
+            georef.cell_width,
+            georef.cell_height,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.input_size.to_le_bytes());
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        bytes.extend_from_slice(&(self.input.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.input.as_bytes());
+        bytes.extend_from_slice(&(self.nodes.len() as u64).to_le_bytes());
+        for node in &self.nodes {
+            let peak = &node.peak;
+            let col = peak.col_x.zip(peak.col_y);
+            bytes.extend_from_slice(&cell(Some((peak.peak_x, peak.peak_y))).to_le_bytes());
+            bytes.extend_from_slice(&peak.peak_elevation.to_le_bytes());
+            bytes.extend_from_slice(&cell(col).to_le_bytes());
+            bytes.extend_from_slice(&peak.col_elevation.unwrap_or(0).to_le_bytes());
+            bytes.extend_from_slice(&cell(node.parent).to_le_bytes());
+        }
+        let hash = fnv1a(FNV_OFFSET, &bytes);
+        bytes.extend_from_slice(&hash.to_le_bytes());
+
+        let mut out = BufWriter::new(File::create(path).map_err(|e| Error::io(path, e))?);
+        out.write_all(&bytes)
+            .and_then(|_| out.flush())
+            .map_err(|e| Error::io(path, e))
+    }
+}
+
+// Build the tree of the grid in `path`, recording the file's size and hash
+pub fn build_divide_tree(path: &str, monitor: &Monitor) -> Result<DivideTree> {
+    let (input_size, checksum) = file_checksum(path)?;
+    let mut source = Registry::default().open(path)?;
+    let georef = source.georef();
+    let grid = source.read_compact_with(monitor)?;
+    Ok(DivideTree {
+        georef,
+        input: path.to_string(),
+        input_size,
+        checksum,
+        ..DivideTree::build(&grid, monitor)?
+    })
+}
+
+pub fn read_divide_tree(path: &str) -> Result<DivideTree> {
+    let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+    let malformed = |message: &str| Error::format(path, format!("divide tree index: {}", message));
+    if bytes.len() < MAGIC.len() + 12 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(malformed("not an index file"));
+    }
+    let (body, trailer) = bytes.split_at(bytes.len() - 8);
+    if fnv1a(FNV_OFFSET, body).to_le_bytes() != trailer {
+        return Err(malformed("checksum mismatch; the file is damaged"));
+    }
+
+    let mut reader = Fields {
+        bytes: &body[MAGIC.len()..],
+    };
+    let mut field = |len: usize| reader.take(len).ok_or_else(|| malformed("truncated"));
+    let version = u32::from_le_bytes(field(4)?.try_into().unwrap());
+    if version != VERSION {
+        return Err(malformed(&format!("unsupported version {}", version)));
+    }
+    let mut u64_field = || field(8).map(|b| u64::from_le_bytes(b.try_into().unwrap()));
+    let rows = u64_field()? as usize;
+    let cols = u64_field()? as usize;
+    let has_georef = field(1)?[0] != 0;
+    let mut f64_field = || field(8).map(|b| f64::from_le_bytes(b.try_into().unwrap()));
+    let georef = Georef {
+        west: f64_field()?,
+        north: f64_field()?,
+        cell_width: f64_field()?,
+        cell_height: f64_field()?,
+    };
+    let mut u64_field = || field(8).map(|b| u64::from_le_bytes(b.try_into().unwrap()));
+    let input_size = u64_field()?;
+    let checksum = u64_field()?;
+    let input_len = u32::from_le_bytes(field(4)?.try_into().unwrap()) as usize;
+    let input = String::from_utf8_lossy(field(input_len)?).into_owned();
+    let count = u64::from_le_bytes(field(8)?.try_into().unwrap()) as usize;
+    let records = field(
+        count
+            .checked_mul(NODE_BYTES)
+            .ok_or_else(|| malformed("size"))?,
+    )?;
+    if !reader.bytes.is_empty() {
+        return Err(malformed("trailing bytes"));
+    }
+
+    let total = rows
+        .checked_mul(cols)
+        .ok_or_else(|| malformed("grid size"))?;
+    let position = |cell: u32| -> Result<Option<(usize, usize)>> {
+        match cell {
+            NONE => Ok(None),
+            cell if (cell as usize) < total => {
+                Ok(Some((cell as usize / cols, cell as usize % cols)))
+            }
+            _ => Err(malformed("cell outside the grid")),
+        }
+    };
+    let mut nodes = Vec::with_capacity(count);
+    for record in records.chunks_exact(NODE_BYTES) {
+        let word = |i: usize| record[i * 4..i * 4 + 4].try_into().unwrap();
+        let (peak_x, peak_y) = position(u32::from_le_bytes(word(0)))?
+            .ok_or_else(|| malformed("peak without summit"))?;
+        let elevation = i32::from_le_bytes(word(1));
+        let col = position(u32::from_le_bytes(word(2)))?;
+        let col_elevation = col.map(|_| i32::from_le_bytes(word(3)));
+        let prominence = elevation
+            .checked_sub(col_elevation.unwrap_or(0))
+            .ok_or_else(|| malformed("col elevation out of range"))?;
+        nodes.push(TreeNode {
+            peak: Peak {
+                prominence,
+                peak_x,
+                peak_y,
+                peak_elevation: elevation,
+                col_x: col.map(|(row, _)| row),
+                col_y: col.map(|(_, col)| col),
+                col_elevation,
+                edge_affected: false,
+            },
+            parent: position(u32::from_le_bytes(word(4)))?,
+        });
+    }
+    Ok(DivideTree {
+        rows,
+        cols,
+        georef: has_georef.then_some(georef),
+        input,
+        input_size,
+        checksum,
+        nodes,
+    })
+}
+
+// Size and FNV-1a hash of a file's bytes
+pub fn file_checksum(path: &str) -> Result<(u64, u64)> {
+    let mut reader = BufReader::new(File::open(path).map_err(|e| Error::io(path, e))?);
+    let mut buffer = vec![0u8; 1 << 20];
+    let (mut size, mut hash) = (0u64, FNV_OFFSET);
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::io(path, e)),
+        };
+        hash = fnv1a(hash, &buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, hash))
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+// Consumes an index body front to back
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (field, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(field)
+    }
+}
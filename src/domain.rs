@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::georef::Georef;
+use crate::grid::Elevations;
+use crate::merge_tree::MergeTree;
+use crate::prominence::Peak;
+
+// Per-mountain statistics over the cells of a peak's domain
+pub struct DomainStats {
+    pub rank: usize,
+    pub area_cells: usize,
+    pub area_km2: Option<f64>,
+    pub mean_elevation: f64,
+    // Sum of cell heights above the key col (above sea level for the highest peak)
+    pub volume_cell_m: f64,
+    pub volume_km3: Option<f64>,
+}
+
+// Label every cell with the rank (1-based position in `peaks`) of the peak
+// dominating it: the summit of the set it joined when activated, followed up
+// the merge tree until a reported peak is reached. 0 where no peak dominates.
+pub fn label_domains(tree: &MergeTree, peaks: &[Peak]) -> Vec<u32> {
+    let ranks: HashMap<usize, u32> = peaks
+        .iter()
+        .enumerate()
+        .map(|(i, peak)| (peak.peak_x * tree.cols + peak.peak_y, (i + 1) as u32))
+        .collect();
+
+    // Resolve each summit once; most cells share a handful of summits
+    let mut resolved: HashMap<usize, u32> = HashMap::new();
+    (0..tree.len())
+        .map(|cell| {
+            let Some(summit) = tree.summit(cell) else {
+                return 0;
+            };
+            *resolved.entry(summit).or_insert_with(|| {
+                let mut s = summit;
+                loop {
+                    if let Some(&rank) = ranks.get(&s) {
+                        break rank;
+                    }
+                    match tree.summit_parent.get(&s) {
+                        Some(&parent) => s = parent,
+                        None => break 0,
+                    }
+                }
+            })
+        })
+        .collect()
+}
+
+pub fn domain_stats<G: Elevations + ?Sized>(
+    grid: &G,
+    peaks: &[Peak],
+    labels: &[u32],
+    georef: Option<&Georef>,
+) -> Vec<DomainStats> {
+    let mut stats: Vec<DomainStats> = (1..=peaks.len())
+        .map(|rank| DomainStats {
+            rank,
+            area_cells: 0,
+            area_km2: georef.map(|_| 0.0),
+            mean_elevation: 0.0,
+            volume_cell_m: 0.0,
+            volume_km3: georef.map(|_| 0.0),
+        })
+        .collect();
+
+    let cols = grid.dimensions().1;
+    for (index, &label) in labels.iter().enumerate() {
+        if label == 0 {
+            continue;
+        }
+        let peak = &peaks[label as usize - 1];
+        let elevation = grid.metres(index);
+        let height = (elevation - peak.col_elevation.unwrap_or(0)).max(0) as f64;
+        let s = &mut stats[label as usize - 1];
+        s.area_cells += 1;
+        s.mean_elevation += elevation as f64;
+        s.volume_cell_m += height;
+        if let Some(g) = georef {
+            let cell_area = g.cell_area_km2(index / cols);
+            *s.area_km2.get_or_insert(0.0) += cell_area;
+            *s.volume_km3.get_or_insert(0.0) += cell_area * height / 1000.0;
+        }
+    }
+
+    for s in &mut stats {
+        if s.area_cells > 0 {
+            s.mean_elevation /= s.area_cells as f64;
+        }
+    }
+    stats
+}
+
+pub fn write_domain_stats_csv<W: Write>(
+    out: &mut W,
+    peaks: &[Peak],
+    stats: &[DomainStats],
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "rank,row,col,elevation,prominence,area_cells,area_km2,mean_elevation,volume_cell_m,volume_km3"
+    )?;
+    for s in stats {
+        let peak = &peaks[s.rank - 1];
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{:.1},{},{}",
+            s.rank,
+            peak.peak_x,
+            peak.peak_y,
+            peak.peak_elevation,
+            peak.prominence,
+            s.area_cells,
+            s.area_km2.map_or(String::new(), |a| format!("{:.3}", a)),
+            s.mean_elevation,
+            s.volume_cell_m,
+            s.volume_km3.map_or(String::new(), |v| format!("{:.6}", v)),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::io;
+
+// Errors produced while reading grids, computing prominence and writing results
+#[derive(Debug)]
+pub enum Error {
+    // Underlying I/O failure, e.g. file not found or permission denied
+    Io {
+        path: Option<String>,
+        source: io::Error,
+    },
+    // File contents disagree with the expected layout or metadata,
+    // e.g. a size mismatch or inconsistent row lengths
+    Format {
+        path: String,
+        message: String,
+    },
+    // A value could not be parsed; `line` and `column` are 1-based
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    // File format or extension not supported for the requested operation
+    UnsupportedFormat(String),
+    // Option or argument outside its valid range
+    InvalidParameter(String),
+    // Run aborted through a `CancelToken`
+    Cancelled,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // Attach the file being processed to an I/O error
+    pub fn io(path: &str, source: io::Error) -> Error {
+        Error::Io {
+            path: Some(path.to_string()),
+            source,
+        }
+    }
+
+    pub fn format(path: &str, message: impl Into<String>) -> Error {
+        Error::Format {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "'{}': {}", path, source),
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::Format { path, message } => write!(f, "'{}': {}", path, message),
+            Error::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "'{}' line {}, column {}: {}",
+                path, line, column, message
+            ),
+            Error::UnsupportedFormat(message) => write!(f, "Unsupported format: {}", message),
+            Error::InvalidParameter(message) => write!(f, "Invalid parameter: {}", message),
+            Error::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Error {
+        Error::Io { path: None, source }
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{self, Write};
+
+use crate::georef::Georef;
+use crate::output::json_string;
+use crate::prominence::Peak;
+
+// GeoJSON FeatureCollection: a Point per peak and key col, and a
+// LineString joining each peak to its col
+pub fn write_geojson<W: Write>(out: &mut W, peaks: &[Peak], georef: &Georef) -> io::Result<()> {
+    let mut features = Vec::new();
+    for (i, peak) in peaks.iter().enumerate() {
+        let rank = i + 1;
+        let (lon, lat) = georef.cell_center(peak.peak_x, peak.peak_y);
+        features.push(format!(
+            "{{\"type\": \"Feature\", \"geometry\": {{\"type\": \"Point\", \"coordinates\": [{:.6}, {:.6}]}}, \
+             \"properties\": {{\"kind\": \"peak\", \"name\": {}, \"rank\": {}, \"prominence\": {}, \"elevation\": {}, \"row\": {}, \"col\": {}}}}}",
+            lon,
+            lat,
+            json_string(&format!("Peak {}", rank)),
+            rank,
+            peak.prominence,
+            peak.peak_elevation,
+            peak.peak_x,
+            peak.peak_y
+        ));
+
+        if let (Some(crow), Some(ccol), Some(celev)) = (peak.col_x, peak.col_y, peak.col_elevation)
+        {
+            let (col_lon, col_lat) = georef.cell_center(crow, ccol);
+            features.push(format!(
+                "{{\"type\": \"Feature\", \"geometry\": {{\"type\": \"Point\", \"coordinates\": [{:.6}, {:.6}]}}, \
+                 \"properties\": {{\"kind\": \"col\", \"rank\": {}, \"elevation\": {}, \"row\": {}, \"col\": {}}}}}",
+                col_lon, col_lat, rank, celev, crow, ccol
+            ));
+            features.push(format!(
+                "{{\"type\": \"Feature\", \"geometry\": {{\"type\": \"LineString\", \"coordinates\": [[{:.6}, {:.6}], [{:.6}, {:.6}]]}}, \
+                 \"properties\": {{\"kind\": \"key_col\", \"rank\": {}, \"prominence\": {}, \"elevation\": {}, \"col_elevation\": {}}}}}",
+                lon, lat, col_lon, col_lat, rank, peak.prominence, peak.peak_elevation, celev
+            ));
+        }
+    }
+
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"type\": \"FeatureCollection\",")?;
+    writeln!(out, "  \"features\": [")?;
+    for (i, feature) in features.iter().enumerate() {
+        let separator = if i + 1 < features.len() { "," } else { "" };
+        writeln!(out, "    {}{}", feature, separator)?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+// KML document with placemarks for peaks, cols and peak-to-col lines
+pub fn write_kml<W: Write>(
+    out: &mut W,
+    peaks: &[Peak],
+    georef: &Georef,
+    title: &str,
+) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<kml xmlns=\"http://www.opengis.net/kml/2.2\">")?;
+    writeln!(out, "<Document>")?;
+    writeln!(out, "  <name>{}</name>", xml_escape(title))?;
+    for (i, peak) in peaks.iter().enumerate() {
+        let rank = i + 1;
+        let (lon, lat) = georef.cell_center(peak.peak_x, peak.peak_y);
+        writeln!(out, "  <Placemark>")?;
+        writeln!(out, "    <name>Peak {}</name>", rank)?;
+        writeln!(
+            out,
+            "    <description>prominence {} m, elevation {} m</description>",
+            peak.prominence, peak.peak_elevation
+        )?;
+        writeln!(
+            out,
+            "    <Point><coordinates>{:.6},{:.6},{}</coordinates></Point>",
+            lon, lat, peak.peak_elevation
+        )?;
+        writeln!(out, "  </Placemark>")?;
+
+        if let (Some(crow), Some(ccol), Some(celev)) = (peak.col_x, peak.col_y, peak.col_elevation)
+        {
+            let (col_lon, col_lat) = georef.cell_center(crow, ccol);
+            writeln!(out, "  <Placemark>")?;
+            writeln!(out, "    <name>Col {}</name>", rank)?;
+            writeln!(
+                out,
+                "    <description>key col of peak {}, elevation {} m</description>",
+                rank, celev
+            )?;
+            writeln!(
+                out,
+                "    <Point><coordinates>{:.6},{:.6},{}</coordinates></Point>",
+                col_lon, col_lat, celev
+            )?;
+            writeln!(out, "  </Placemark>")?;
+            writeln!(out, "  <Placemark>")?;
+            writeln!(out, "    <name>Peak {} to col</name>", rank)?;
+            writeln!(
+                out,
+                "    <LineString><coordinates>{:.6},{:.6},{} {:.6},{:.6},{}</coordinates></LineString>",
+                lon, lat, peak.peak_elevation, col_lon, col_lat, celev
+            )?;
+            writeln!(out, "  </Placemark>")?;
+        }
+    }
+    writeln!(out, "</Document>")?;
+    writeln!(out, "</kml>")?;
+    Ok(())
+}
+
+// GPX waypoints for peaks and their key cols
+pub fn write_gpx<W: Write>(
+    out: &mut W,
+    peaks: &[Peak],
+    georef: &Georef,
+    title: &str,
+) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        out,
+        "<gpx version=\"1.1\" creator=\"topographic_prominence\" xmlns=\"http://www.topografix.com/GPX/1/1\">"
+    )?;
+    writeln!(
+        out,
+        "  <metadata><name>{}</name></metadata>",
+        xml_escape(title)
+    )?;
+    for (i, peak) in peaks.iter().enumerate() {
+        let rank = i + 1;
+        let (lon, lat) = georef.cell_center(peak.peak_x, peak.peak_y);
+        write_waypoint(
+            out,
+            lat,
+            lon,
+            peak.peak_elevation,
+            &format!("Peak {}", rank),
+            &format!("prominence {} m", peak.prominence),
+            "peak",
+        )?;
+        if let (Some(crow), Some(ccol), Some(celev)) = (peak.col_x, peak.col_y, peak.col_elevation)
+        {
+            let (col_lon, col_lat) = georef.cell_center(crow, ccol);
+            write_waypoint(
+                out,
+                col_lat,
+                col_lon,
+                celev,
+                &format!("Col {}", rank),
+                &format!("key col of peak {}", rank),
+                "col",
+            )?;
+        }
+    }
+    writeln!(out, "</gpx>")?;
+    Ok(())
+}
+
+fn write_waypoint<W: Write>(
+    out: &mut W,
+    lat: f64,
+    lon: f64,
+    elevation: i32,
+    name: &str,
+    desc: &str,
+    kind: &str,
+) -> io::Result<()> {
+    writeln!(out, "  <wpt lat=\"{:.6}\" lon=\"{:.6}\">", lat, lon)?;
+    writeln!(out, "    <ele>{}</ele>", elevation)?;
+    writeln!(out, "    <name>{}</name>", xml_escape(name))?;
+    writeln!(out, "    <desc>{}</desc>", xml_escape(desc))?;
+    writeln!(out, "    <type>{}</type>", kind)?;
+    writeln!(out, "  </wpt>")?;
+    Ok(())
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,239 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+// GTOPO30/GMTED tiles are sampled every 30 arc-seconds
+const TILE_CELL_DEGREES: f64 = 30.0 / 3600.0;
+
+// Mean Earth radius used for cell areas
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+// Mapping from grid rows/cols to longitude/latitude.
+// Row 0 is the northern edge, rows increase southwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Georef {
+    pub west: f64,
+    pub north: f64,
+    pub cell_width: f64,
+    pub cell_height: f64,
+}
+
+impl Georef {
+    // Fallback used when the input carries no georeference: one unit per cell
+    pub const GRID: Georef = Georef {
+        west: 0.0,
+        north: 0.0,
+        cell_width: 1.0,
+        cell_height: 1.0,
+    };
+
+    // Longitude/latitude of the centre of a cell
+    pub fn cell_center(&self, row: usize, col: usize) -> (f64, f64) {
+        let lon = self.west + (col as f64 + 0.5) * self.cell_width;
+        let lat = self.north - (row as f64 + 0.5) * self.cell_height;
+        (lon, lat)
+    }
+
+    // Longitude/latitude of a fractional (row, col) position, cell centres at integers
+    pub fn point(&self, row: f64, col: f64) -> (f64, f64) {
+        let lon = self.west + (col + 0.5) * self.cell_width;
+        let lat = self.north - (row + 0.5) * self.cell_height;
+        (lon, lat)
+    }
+
+    // Cell of a `rows` x `cols` grid containing a longitude/latitude, if any
+    pub fn cell_at(&self, lon: f64, lat: f64, rows: usize, cols: usize) -> Option<(usize, usize)> {
+        let row = ((self.north - lat) / self.cell_height).floor();
+        let col = ((lon - self.west) / self.cell_width).floor();
+        (row >= 0.0 && row < rows as f64 && col >= 0.0 && col < cols as f64)
+            .then_some((row as usize, col as usize))
+    }
+
+    // Surface area of one cell in the given row, on a spherical Earth
+    pub fn cell_area_km2(&self, row: usize) -> f64 {
+        let top = (self.north - row as f64 * self.cell_height).to_radians();
+        let bottom = (self.north - (row + 1) as f64 * self.cell_height).to_radians();
+        EARTH_RADIUS_KM
+            * EARTH_RADIUS_KM
+            * self.cell_width.to_radians()
+            * (top.sin() - bottom.sin()).abs()
+    }
+
+    // Look for a `.pat` sidecar next to the input, then fall back to the tile name
+    pub fn locate(filename: &str) -> Result<Option<Georef>> {
+        let path = Path::new(filename);
+        let pat = path.with_extension("pat");
+        if pat.is_file() {
+            return Georef::from_pat(&pat);
+        }
+        Ok(path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(Georef::from_tile_name))
+    }
+
+    // Georeference from the `zone` and `elem_width`/`elem_height` entries of a PAT header
+    pub fn from_pat(path: &Path) -> Result<Option<Georef>> {
+        Ok(PatHeader::read(path)?.georef)
+    }
+
+    // Tiles are named after their north-west corner, e.g. W100N40
+    pub fn from_tile_name(name: &str) -> Option<Georef> {
+        let name = name.to_ascii_uppercase();
+        let (lon_part, lat_part) = name.split_at(name.find(['N', 'S'])?);
+        let lon: f64 = lon_part.get(1..)?.parse().ok()?;
+        let lat: f64 = lat_part.get(1..)?.parse().ok()?;
+        let west = match lon_part.chars().next()? {
+            'W' => -lon,
+            'E' => lon,
+            _ => return None,
+        };
+        let north = if lat_part.starts_with('S') { -lat } else { lat };
+        Some(Georef {
+            west,
+            north,
+            cell_width: TILE_CELL_DEGREES,
+            cell_height: TILE_CELL_DEGREES,
+        })
+    }
+}
+
+// Metadata from a PAT header sidecar. `zone` gives the bounds (south, north,
+// west, east) in degrees, `elem_width`/`elem_height` the cell size in arc-seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PatHeader {
+    pub georef: Option<Georef>,
+    // Grid size from `nrows`/`ncols`, or implied by the zone and cell size
+    pub rows: Option<usize>,
+    pub cols: Option<usize>,
+    pub no_data: Option<i32>,
+}
+
+impl PatHeader {
+    pub fn read(path: &Path) -> Result<PatHeader> {
+        let content =
+            fs::read_to_string(path).map_err(|e| Error::io(&path.display().to_string(), e))?;
+        let mut zone = None;
+        let mut elem_width = None;
+        let mut elem_height = None;
+        let mut no_data = None;
+        let mut rows = None;
+        let mut cols = None;
+
+        for (line_number, line) in content.lines().enumerate() {
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            match key {
+                "zone" => {
+                    let bounds: Vec<f64> = value
+                        .split(',')
+                        .map(|s| s.trim().parse())
+                        .collect::<std::result::Result<_, _>>()
+                        .map_err(|_| bad_pat(path, line_number, "zone"))?;
+                    if bounds.len() != 4 {
+                        return Err(bad_pat(path, line_number, "zone"));
+                    }
+                    zone = Some((bounds[0], bounds[1], bounds[2], bounds[3]));
+                }
+                "elem_width" => {
+                    elem_width = Some(
+                        value
+                            .parse::<f64>()
+                            .map_err(|_| bad_pat(path, line_number, key))?,
+                    )
+                }
+                "elem_height" => {
+                    elem_height = Some(
+                        value
+                            .parse::<f64>()
+                            .map_err(|_| bad_pat(path, line_number, key))?,
+                    )
+                }
+                "nrows" | "ncols" => {
+                    let n = value
+                        .parse::<usize>()
+                        .map_err(|_| bad_pat(path, line_number, key))?;
+                    if key == "nrows" {
+                        rows = Some(n);
+                    } else {
+                        cols = Some(n);
+                    }
+                }
+                "no_data" => {
+                    no_data = Some(
+                        value
+                            .parse::<i32>()
+                            .map_err(|_| bad_pat(path, line_number, key))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let cell_width = elem_width.map_or(TILE_CELL_DEGREES, |w| w / 3600.0);
+        let cell_height = elem_height.map_or(TILE_CELL_DEGREES, |h| h / 3600.0);
+        let cells = |extent: f64, cell: f64| {
+            let n = (extent / cell).round();
+            (n >= 1.0).then_some(n as usize)
+        };
+        Ok(PatHeader {
+            georef: zone.map(|(_south, north, west, _east)| Georef {
+                west,
+                north,
+                cell_width,
+                cell_height,
+            }),
+            rows: rows.or(zone.and_then(|(south, north, _, _)| cells(north - south, cell_height))),
+            cols: cols.or(zone.and_then(|(_, _, west, east)| cells(east - west, cell_width))),
+            no_data,
+        })
+    }
+}
+
+impl PatHeader {
+    // Write a header for a 16-bit binary grid named `name` holding values in `range`
+    pub fn write<W: Write>(&self, out: &mut W, name: &str, range: (i32, i32)) -> io::Result<()> {
+        writeln!(out, "PAT META 2 1.0")?;
+        writeln!(out, "###")?;
+        writeln!(out, "name\t\t{}", name)?;
+        if let (Some(georef), Some(rows), Some(cols)) = (self.georef, self.rows, self.cols) {
+            writeln!(
+                out,
+                "zone\t\t{}, {}, {}, {}",
+                georef.north - rows as f64 * georef.cell_height,
+                georef.north,
+                georef.west,
+                georef.west + cols as f64 * georef.cell_width
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(out, "### 2D details")?;
+        writeln!(out, "flags\t\tsigned north-south")?;
+        writeln!(out, "format\t\tbinary")?;
+        writeln!(out, "elem_bits\t16")?;
+        if let (Some(rows), Some(cols)) = (self.rows, self.cols) {
+            writeln!(out, "nrows\t\t{}", rows)?;
+            writeln!(out, "ncols\t\t{}", cols)?;
+        }
+        if let Some(georef) = self.georef {
+            writeln!(out, "elem_width\t{}", georef.cell_width * 3600.0)?;
+            writeln!(out, "elem_height\t{}", georef.cell_height * 3600.0)?;
+        }
+        if let Some(no_data) = self.no_data {
+            writeln!(out, "no_data\t\t{}", no_data)?;
+        }
+        writeln!(out, "range\t\t{} {}", range.0, range.1)
+    }
+}
+
+fn bad_pat(path: &Path, line_number: usize, key: &str) -> Error {
+    Error::Parse {
+        path: path.display().to_string(),
+        line: line_number + 1,
+        column: 1,
+        message: format!("invalid '{}' entry", key),
+    }
+}
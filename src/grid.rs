@@ -0,0 +1,255 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::georef::{Georef, PatHeader};
+use crate::raster::{self, Raster, RasterKind};
+use crate::source::{BinSource, GridSource, Registry};
+
+// Elevation grid stored row-major in a flat vector
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<i32>,
+}
+
+// Most cells a grid may have: the sweep numbers cells with u32 and keeps
+// u32::MAX free
+pub const MAX_CELLS: usize = u32::MAX as usize - 1;
+
+// Cell count of a `rows` x `cols` grid, if it is no more than `MAX_CELLS`
+pub(crate) fn cell_count(rows: usize, cols: usize) -> Result<usize> {
+    rows.checked_mul(cols)
+        .filter(|&total| total <= MAX_CELLS)
+        .ok_or_else(|| {
+            Error::InvalidParameter(format!(
+                "{}x{} grid is larger than {} cells",
+                rows, cols, MAX_CELLS
+            ))
+        })
+}
+
+// Check that `len` values fill a `rows` x `cols` grid of at most `MAX_CELLS`
+pub(crate) fn check_size(rows: usize, cols: usize, len: usize) -> Result<()> {
+    let total = cell_count(rows, cols)?;
+    if total != len {
+        return Err(Error::InvalidParameter(format!(
+            "{}x{} grid needs {} values, got {}",
+            rows, cols, total, len
+        )));
+    }
+    Ok(())
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize, data: Vec<i32>) -> Result<Grid> {
+        check_size(rows, cols, data.len())?;
+        Ok(Grid { rows, cols, data })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn elevation(&self, row: usize, col: usize) -> i32 {
+        self.data[row * self.cols + col]
+    }
+}
+
+// Read access to elevations needed by the prominence sweep, so it can run
+// on `Grid` or on a `CompactGrid` holding narrower samples
+pub trait Elevations {
+    // (rows, cols)
+    fn dimensions(&self) -> (usize, usize);
+
+    // Elevation of cell `index` (row-major) in whole metres
+    fn metres(&self, index: usize) -> i32;
+
+    // Key of cell `index` that orders cells like their exact elevations
+    fn key(&self, index: usize) -> u32;
+}
+
+impl Elevations for Grid {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn metres(&self, index: usize) -> i32 {
+        self.data[index]
+    }
+
+    fn key(&self, index: usize) -> u32 {
+        self.data[index] as u32 ^ 0x8000_0000
+    }
+}
+
+// Read a grid with the built-in readers, detecting the format from magic
+// bytes or the file extension
+pub fn read_grid(filename: &str) -> Result<Grid> {
+    Registry::default().read(filename)
+}
+
+// Read CSV into flat 1D grid
+pub fn read_csv_grid(filename: &str) -> Result<Grid> {
+    let content = std::fs::read_to_string(filename).map_err(|e| Error::io(filename, e))?;
+    let mut grid = Vec::new();
+    let mut rows: usize = 0;
+    let mut cols: usize = 0;
+
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = line
+            .split(',')
+            .enumerate()
+            .map(|(column, s)| {
+                s.trim().parse::<i32>().map_err(|_| Error::Parse {
+                    path: filename.to_string(),
+                    line: line_number + 1,
+                    column: column + 1,
+                    message: format!("invalid number '{}'", s.trim()),
+                })
+            })
+            .collect::<Result<Vec<i32>>>()?;
+        if cols == 0 {
+            cols = row.len();
+        } else if cols != row.len() {
+            return Err(Error::format(
+                filename,
+                format!(
+                    "inconsistent row length in CSV: line {} has {} values, expected {}",
+                    line_number + 1,
+                    row.len(),
+                    cols
+                ),
+            ));
+        }
+        grid.extend(row);
+        rows += 1;
+    }
+
+    if rows == 0 || cols == 0 {
+        return Err(Error::format(filename, "empty CSV grid"));
+    }
+
+    let total: usize = rows
+        .checked_mul(cols)
+        .ok_or_else(|| Error::format(filename, "grid size too large"))?;
+
+    if total != grid.len() {
+        return Err(Error::format(filename, "CSV grid size mismatch"));
+    }
+
+    crate::info!(
+        "Read CSV grid: rows={}, cols={}, total={}",
+        rows,
+        cols,
+        total
+    );
+    Ok(Grid {
+        rows,
+        cols,
+        data: grid,
+    })
+}
+
+// Read binary grid into flat 1D grid
+pub fn read_bin_grid(filename: &str) -> Result<Grid> {
+    BinSource::open(filename)?.read_all()
+}
+
+// Write a grid in the format given by the extension: .csv, .asc, or .bin with
+// a .pat header sidecar carrying the georeference
+pub fn write_grid(
+    path: &str,
+    grid: &Grid,
+    georef: Option<&Georef>,
+    no_data: Option<i32>,
+) -> Result<()> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let range = (
+        grid.data.iter().copied().min().unwrap_or(0),
+        grid.data.iter().copied().max().unwrap_or(0),
+    );
+    let create = |path: &str| File::create(path).map(BufWriter::new);
+
+    let written = match extension.as_deref() {
+        Some("csv") => create(path).and_then(|mut out| {
+            write_csv_grid(&mut out, grid)?;
+            out.flush()
+        }),
+        Some("asc") => create(path).and_then(|mut out| {
+            let raster = Raster {
+                rows: grid.rows,
+                cols: grid.cols,
+                values: grid.data.clone(),
+                georef: georef.copied(),
+                kind: RasterKind::Values,
+                no_data,
+            };
+            raster::write_ascii_grid(&mut out, &raster)?;
+            out.flush()
+        }),
+        Some("bin") => {
+            if range.0 < i16::MIN as i32 || range.1 > i16::MAX as i32 {
+                return Err(Error::InvalidParameter(format!(
+                    "elevations {}..{} do not fit a 16-bit .bin grid",
+                    range.0, range.1
+                )));
+            }
+            create(path).and_then(|mut out| {
+                write_bin_grid(&mut out, grid)?;
+                out.flush()
+            })?;
+            let pat = Path::new(path).with_extension("pat");
+            let header = PatHeader {
+                georef: georef.copied(),
+                rows: Some(grid.rows),
+                cols: Some(grid.cols),
+                no_data,
+            };
+            let name = pat.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let pat_path = pat.display().to_string();
+            return create(&pat_path)
+                .and_then(|mut out| {
+                    header.write(&mut out, name, range)?;
+                    out.flush()
+                })
+                .map_err(|e| Error::io(&pat_path, e));
+        }
+        _ => {
+            return Err(Error::UnsupportedFormat(format!(
+                "'{}': unsupported grid extension. Use .csv, .bin or .asc",
+                path
+            )))
+        }
+    };
+    written.map_err(|e| Error::io(path, e))
+}
+
+// One grid row per line, comma separated
+pub fn write_csv_grid<W: Write>(out: &mut W, grid: &Grid) -> io::Result<()> {
+    for row in grid.data.chunks(grid.cols) {
+        let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(out, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+// Little-endian i16 samples; values must already fit 16 bits
+pub fn write_bin_grid<W: Write>(out: &mut W, grid: &Grid) -> io::Result<()> {
+    for &elevation in &grid.data {
+        out.write_all(&(elevation as i16).to_le_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::georef::Georef;
+use crate::grid::Elevations;
+use crate::merge_tree::MergeTree;
+use crate::output::json_string;
+use crate::prominence::Peak;
+
+// A peak's prominence island: the cells above its key col that were connected
+// to it when it merged into higher ground
+pub struct Island {
+    pub rank: usize,
+    pub prominence: i32,
+    pub col_elevation: Option<i32>,
+    pub area_cells: usize,
+    pub area_km2: Option<f64>,
+    // Each polygon is an outer ring followed by its holes, in (row, col) units
+    pub polygons: Vec<Vec<Vec<(f64, f64)>>>,
+}
+
+// Label every cell with the rank (1-based position in `peaks`) of the
+// innermost island containing it, or 0 if none does. A cell belongs to an
+// island if it was in the peak's set at the merge and lies above the col.
+pub fn label_islands<G: Elevations + ?Sized>(
+    tree: &MergeTree,
+    grid: &G,
+    peaks: &[Peak],
+) -> Vec<u32> {
+    let (roots, top) = island_roots(tree, peaks);
+    let nearest = nearest_roots(tree, &roots);
+    let mut labels = vec![0u32; tree.len()];
+    for (cell, label) in labels.iter_mut().enumerate() {
+        // Only a cell level with an island's col falls through to the next
+        let mut r = nearest[cell];
+        *label = loop {
+            if r == NO_ROOT {
+                break top;
+            }
+            let rank = roots[&r];
+            if peaks[rank as usize - 1].col_elevation < Some(grid.metres(cell)) {
+                break rank;
+            }
+            r = root_above(tree, &nearest, r);
+        };
+    }
+    labels
+}
+
+// Measure and vectorise the island of every peak from an island label raster
+pub fn trace_islands<G: Elevations + ?Sized>(
+    tree: &MergeTree,
+    grid: &G,
+    peaks: &[Peak],
+    labels: &[u32],
+    georef: Option<&Georef>,
+) -> Vec<Island> {
+    let (rows, cols) = (tree.rows, tree.cols);
+    let (roots, top) = island_roots(tree, peaks);
+    let nearest = nearest_roots(tree, &roots);
+
+    // Parent island of each island: the next island found above its root.
+    // Islands without one hang from 0, which stands for the whole grid.
+    let n = peaks.len() + 1;
+    let mut parent = vec![0usize; n];
+    for (&root, &rank) in &roots {
+        parent[rank as usize] = match root_above(tree, &nearest, root) {
+            NO_ROOT => top as usize,
+            above => roots[&above] as usize,
+        };
+    }
+
+    // Number islands in depth-first order, so that the islands nested in
+    // one are those numbered from its `enter` up to its `exit`
+    let mut children = vec![Vec::new(); n];
+    for rank in 1..n {
+        children[parent[rank]].push(rank);
+    }
+    let (mut enter, mut exit) = (vec![0usize; n], vec![0usize; n]);
+    let mut depth = vec![0usize; n];
+    let mut order = Vec::with_capacity(n);
+    let mut stack = vec![(0, false)];
+    while let Some((island, done)) = stack.pop() {
+        if done {
+            exit[island] = order.len();
+            continue;
+        }
+        enter[island] = order.len();
+        order.push(island);
+        stack.push((island, true));
+        for &child in &children[island] {
+            depth[child] = depth[island] + 1;
+            stack.push((child, false));
+        }
+    }
+    let contains = |island: usize, label: usize| {
+        label != 0 && enter[island] <= enter[label] && enter[label] < exit[island]
+    };
+
+    // Cells and area labelled with each island
+    let mut area_cells = vec![0usize; n];
+    let mut area_km2 = vec![0f64; n];
+    for x in 0..rows {
+        let cell_area = georef.map_or(0.0, |g| g.cell_area_km2(x));
+        for y in 0..cols {
+            let label = labels[x * cols + y] as usize;
+            if label == 0 {
+                continue;
+            }
+            area_cells[label] += 1;
+            area_km2[label] += cell_area;
+        }
+    }
+    // then including the islands nested in it, children before parents
+    for &island in order.iter().skip(1).rev() {
+        let up = parent[island];
+        area_cells[up] += area_cells[island];
+        area_km2[up] += area_km2[island];
+    }
+
+    // Marching squares over the lattice of cell centres, which runs one cell
+    // beyond the grid on every side, for every island in one pass. A square
+    // holds boundary of the islands containing some but not all of its
+    // corners: those from each corner's island up to the innermost island
+    // containing all four.
+    let width = cols + 2;
+    let label_at = |i: usize, j: usize| {
+        if i == 0 || j == 0 || i > rows || j > cols {
+            0
+        } else {
+            labels[(i - 1) * cols + j - 1] as usize
+        }
+    };
+    let mut segments = vec![Vec::new(); n];
+    let mut seen = vec![usize::MAX; n];
+    let mut crossed = Vec::new();
+    for i in 0..=rows {
+        for j in 0..=cols {
+            let corners = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)];
+            let corner_labels = corners.map(|(a, c)| label_at(a, c));
+            if corner_labels.iter().all(|&label| label == corner_labels[0]) {
+                continue;
+            }
+            let stop = if corner_labels.contains(&0) {
+                0
+            } else {
+                corner_labels[1..].iter().fold(corner_labels[0], |a, &b| {
+                    innermost_common(&parent, &depth, a, b)
+                })
+            };
+            let square = i * width + j;
+            crossed.clear();
+            for &label in &corner_labels {
+                let mut island = label;
+                while island != stop && seen[island] != square {
+                    seen[island] = square;
+                    crossed.push(island);
+                    island = parent[island];
+                }
+            }
+
+            for &island in &crossed {
+                let state = corner_labels.map(|label| contains(island, label));
+                let level = peaks[island - 1].col_elevation.unwrap_or(i32::MIN);
+                for k in 0..4 {
+                    if !state[k] || state[(k + 1) % 4] {
+                        continue;
+                    }
+                    // Directed segment from the side where the boundary
+                    // leaves the inside (walking the square clockwise) to
+                    // the next side where it re-enters, cutting off the
+                    // outside corner
+                    let entry = (1..4)
+                        .map(|d| (k + d) % 4)
+                        .find(|&e| !state[e] && state[(e + 1) % 4])
+                        .expect("exit without matching entry");
+                    let point = crossing(grid, corners[k], corners[(k + 1) % 4], level);
+                    segments[island].push((
+                        lattice_side(i, j, k, width),
+                        lattice_side(i, j, entry, width),
+                        point,
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut islands = Vec::new();
+    for rank in 1..n {
+        let peak = &peaks[rank - 1];
+        let rings = stitch_rings(&std::mem::take(&mut segments[rank]));
+        islands.push(Island {
+            rank,
+            prominence: peak.prominence,
+            col_elevation: peak.col_elevation,
+            area_cells: area_cells[rank],
+            area_km2: georef.map(|_| area_km2[rank]),
+            polygons: assemble_polygons(rings),
+        });
+    }
+    islands
+}
+
+// Write island polygons as a GeoJSON FeatureCollection
+pub fn write_islands_geojson<W: Write>(
+    out: &mut W,
+    islands: &[Island],
+    peaks: &[Peak],
+    georef: &Georef,
+) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"type\": \"FeatureCollection\",")?;
+    writeln!(out, "  \"features\": [")?;
+    for (i, island) in islands.iter().enumerate() {
+        let peak = &peaks[island.rank - 1];
+        let polygons: Vec<String> = island
+            .polygons
+            .iter()
+            .map(|rings| {
+                let rings: Vec<String> = rings
+                    .iter()
+                    .map(|ring| {
+                        // Latitude runs opposite to rows, so reverse to keep
+                        // outer rings counter-clockwise as GeoJSON expects
+                        let points: Vec<String> = ring
+                            .iter()
+                            .rev()
+                            .map(|&(x, y)| {
+                                let (lon, lat) = georef.point(x, y);
+                                format!("[{:.6}, {:.6}]", lon, lat)
+                            })
+                            .collect();
+                        format!("[{}]", points.join(", "))
+                    })
+                    .collect();
+                format!("[{}]", rings.join(", "))
+            })
+            .collect();
+        let separator = if i + 1 < islands.len() { "," } else { "" };
+        writeln!(
+            out,
+            "    {{\"type\": \"Feature\", \"geometry\": {{\"type\": \"MultiPolygon\", \"coordinates\": [{}]}}, \
+             \"properties\": {{\"name\": {}, \"rank\": {}, \"prominence\": {}, \"elevation\": {}, \"row\": {}, \"col\": {}, \
+             \"col_elevation\": {}, \"area_cells\": {}, \"area_km2\": {}}}}}{}",
+            polygons.join(", "),
+            json_string(&format!("Peak {}", island.rank)),
+            island.rank,
+            island.prominence,
+            peak.peak_elevation,
+            peak.peak_x,
+            peak.peak_y,
+            island.col_elevation.map_or("null".to_string(), |e| e.to_string()),
+            island.area_cells,
+            island.area_km2.map_or("null".to_string(), |a| format!("{:.3}", a)),
+            separator
+        )?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+// Root of each reported island mapped to its rank, plus the rank of the
+// highest peak, whose island is the whole grid
+fn island_roots(tree: &MergeTree, peaks: &[Peak]) -> (HashMap<usize, u32>, u32) {
+    let mut roots = HashMap::new();
+    for (i, peak) in peaks.iter().enumerate() {
+        let index = peak.peak_x * tree.cols + peak.peak_y;
+        if peak.col_elevation.is_some() {
+            if let Some(&root) = tree.islands.get(&index) {
+                roots.insert(root, (i + 1) as u32);
+            }
+        }
+    }
+    (roots, top_rank(peaks) as u32)
+}
+
+// Stands for no island root above a merge node
+const NO_ROOT: usize = usize::MAX;
+
+// Nearest island root at or above every merge node, or NO_ROOT. Each chain
+// of links is walked once.
+fn nearest_roots(tree: &MergeTree, roots: &HashMap<usize, u32>) -> Vec<usize> {
+    const UNKNOWN: usize = usize::MAX - 1;
+    let mut nearest = vec![UNKNOWN; tree.len()];
+    let mut chain = Vec::new();
+    for node in 0..nearest.len() {
+        let mut r = node;
+        let found = loop {
+            if nearest[r] != UNKNOWN {
+                break nearest[r];
+            }
+            if roots.contains_key(&r) {
+                nearest[r] = r;
+                break r;
+            }
+            chain.push(r);
+            if tree.link(r) == r {
+                break NO_ROOT;
+            }
+            r = tree.link(r);
+        };
+        for visited in chain.drain(..) {
+            nearest[visited] = found;
+        }
+    }
+    nearest
+}
+
+// Nearest island root strictly above the island root `root`
+fn root_above(tree: &MergeTree, nearest: &[usize], root: usize) -> usize {
+    match tree.link(root) {
+        up if up == root => NO_ROOT,
+        up => nearest[up],
+    }
+}
+
+fn top_rank(peaks: &[Peak]) -> usize {
+    peaks
+        .iter()
+        .position(|peak| peak.col_elevation.is_none())
+        .map_or(0, |i| i + 1)
+}
+
+// Innermost island containing islands `a` and `b`, or 0 for the whole grid
+fn innermost_common(parent: &[usize], depth: &[usize], mut a: usize, mut b: usize) -> usize {
+    while depth[a] > depth[b] {
+        a = parent[a];
+    }
+    while depth[b] > depth[a] {
+        b = parent[b];
+    }
+    while a != b {
+        a = parent[a];
+        b = parent[b];
+    }
+    a
+}
+
+// Identifier of side `k` of the lattice square with top-left node (i, j),
+// sides numbered clockwise from the top. The two squares sharing a side give
+// it the same identifier.
+fn lattice_side(i: usize, j: usize, k: usize, width: usize) -> usize {
+    let (node, vertical) = match k {
+        0 => (i * width + j, 0),
+        1 => (i * width + j + 1, 1),
+        2 => ((i + 1) * width + j, 0),
+        _ => (i * width + j, 1),
+    };
+    node * 2 + vertical
+}
+
+// Crossing of the `level` contour on the lattice side from the inside node
+// `a` to the outside node `o`, in (row, col) units. Nodes off the grid and
+// level ground put it halfway.
+fn crossing<G: Elevations + ?Sized>(
+    grid: &G,
+    a: (usize, usize),
+    o: (usize, usize),
+    level: i32,
+) -> (f64, f64) {
+    let (rows, cols) = grid.dimensions();
+    let elevation = |(i, j): (usize, usize)| {
+        (i >= 1 && j >= 1 && i <= rows && j <= cols).then(|| grid.metres((i - 1) * cols + j - 1))
+    };
+    let t = match (elevation(a), elevation(o)) {
+        (Some(ea), Some(eo)) if ea != eo => {
+            ((ea as f64 - level as f64) / (ea as f64 - eo as f64)).clamp(0.0, 1.0)
+        }
+        _ => 0.5,
+    };
+    let x = a.0 as f64 + (o.0 as f64 - a.0 as f64) * t - 1.0;
+    let y = a.1 as f64 + (o.1 as f64 - a.1 as f64) * t - 1.0;
+    (x, y)
+}
+
+// Join an island's directed segments (start side, end side, point) into
+// closed rings, each segment continuing with the one starting on its end side
+fn stitch_rings(segments: &[(usize, usize, (f64, f64))]) -> Vec<Vec<(f64, f64)>> {
+    let mut starting: HashMap<usize, usize> = segments
+        .iter()
+        .enumerate()
+        .map(|(s, &(start, _, _))| (start, s))
+        .collect();
+    let mut rings = Vec::new();
+    for &(start, _, _) in segments {
+        let mut ring = Vec::new();
+        let mut side = start;
+        while let Some(s) = starting.remove(&side) {
+            let (_, next, point) = segments[s];
+            ring.push(point);
+            side = next;
+        }
+        if let Some(&first) = ring.first() {
+            ring.push(first);
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+// Polygons of outer rings with the holes inside them; outer rings and holes
+// wind in opposite directions
+fn assemble_polygons(rings: Vec<Vec<(f64, f64)>>) -> Vec<Vec<Vec<(f64, f64)>>> {
+    let (outers, holes): (Vec<_>, Vec<_>) = rings.into_iter().partition(|r| signed_area(r) > 0.0);
+    let mut polygons: Vec<Vec<Vec<(f64, f64)>>> = outers.into_iter().map(|r| vec![r]).collect();
+    for hole in holes {
+        let owner = polygons
+            .iter()
+            .position(|p| point_in_ring(hole[0], &p[0]))
+            .unwrap_or(0);
+        if let Some(polygon) = polygons.get_mut(owner) {
+            polygon.push(hole);
+        }
+    }
+    polygons
+}
+
+// Shoelace area with rows as y and cols as x; positive for the orientation
+// produced for outer boundaries
+fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2)
+        .map(|p| p[0].1 * p[1].0 - p[1].1 * p[0].0)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn point_in_ring(p: (f64, f64), ring: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let (a, b) = (edge[0], edge[1]);
+        if (a.0 > p.0) != (b.0 > p.0) {
+            let y = a.1 + (p.0 - a.0) / (b.0 - a.0) * (b.1 - a.1);
+            if p.1 < y {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
@@ -0,0 +1,89 @@
+//! Topographic prominence of every peak in a digital elevation model.
+//!
+//! Grids are read with [`read_grid`] (or the format-specific readers) and
+//! passed to [`compute_prominence`] together with [`ProminenceOptions`]:
+//!
+//! ```no_run
+//! use topographic_prominence::{compute_prominence, read_grid, ProminenceOptions};
+//!
+//! let grid = read_grid("simple5x5.csv")?;
+//! let result = compute_prominence(&grid, &ProminenceOptions::new().max_peaks(10));
+//! for peak in &result.peaks {
+//!     println!("{} m at row {}, col {}", peak.prominence, peak.peak_x, peak.peak_y);
+//! }
+//! # Ok::<(), topographic_prominence::Error>(())
+//! ```
+//!
+//! [`compute_prominence`] accepts any [`Elevations`]: a [`Grid`] of `i32`
+//! values, or a [`CompactGrid`] keeping 16-bit DEMs at their native width
+//! (see [`read_compact_grid`]).
+//!
+//! [`for_each_peak`] runs the same sweep but hands over each peak as soon as
+//! its key col is found, so large grids can be filtered or written out
+//! without holding every peak, and stopped early.
+//!
+//! A [`Window`] of a larger grid can be read on its own with
+//! [`GridSource::read_compact_window`]; setting its open sides with
+//! [`ProminenceOptions::open_edges`] flags the peaks whose key col may lie
+//! outside it.
+//!
+//! A [`Region`] read from GeoJSON or WKT turns into a cell [`Mask`], which
+//! can pick the peaks whose summits lie in it, or with
+//! [`ProminenceOptions::within`] make its boundary the edge of the world.
+//!
+//! [`batch`] runs whole directories of tiles in parallel, resuming runs that
+//! were interrupted.
+//!
+//! A [`DivideTree`] keeps every peak with its key col and parent summit, and
+//! can be saved to an index file that answers peak queries without the grid.
+//!
+//! [`bottleneck`](mod@bottleneck) finds the highest saddle between any two
+//! cells from the [`MergeTree`] of a sweep, and a ridge route through it.
+//!
+//! Further input formats implement [`GridFormat`] and [`GridSource`] and are
+//! added to a [`Registry`], which picks the reader by magic bytes or extension.
+//!
+//! [`synth`] generates reproducible synthetic terrain of any size for tests
+//! and benchmarks, and [`reference`](mod@reference) holds a slow, obviously correct
+//! prominence computation to check the sweep against.
+
+pub mod batch;
+pub mod bottleneck;
+pub mod compact;
+pub mod diff;
+pub mod divide_tree;
+pub mod domain;
+pub mod error;
+pub mod export;
+pub mod georef;
+pub mod grid;
+pub mod island;
+pub mod json;
+pub mod log;
+pub mod merge_tree;
+pub mod output;
+pub mod progress;
+pub mod prominence;
+pub mod raster;
+pub mod reference;
+pub mod region;
+pub mod render;
+pub mod source;
+pub mod synth;
+pub mod timing;
+pub mod window;
+
+pub use compact::{read_compact_grid, CompactGrid, Samples};
+pub use divide_tree::{read_divide_tree, DivideTree};
+pub use error::{Error, Result};
+pub use georef::Georef;
+pub use grid::{read_bin_grid, read_csv_grid, read_grid, write_grid, Elevations, Grid};
+pub use merge_tree::MergeTree;
+pub use progress::{CancelToken, Monitor, Phase};
+pub use prominence::{
+    compute_prominence, compute_prominence_with, for_each_peak, for_each_peak_with, Peak,
+    Prominence, ProminenceOptions,
+};
+pub use region::{read_region, Mask, Region};
+pub use source::{GridFormat, GridSource, Registry};
+pub use window::{Edges, Window};
@@ -0,0 +1,96 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+// Severity of a diagnostic message, from most to least important
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    fn from_u8(value: u8) -> Level {
+        match value {
+            0 => Level::Error,
+            1 => Level::Warn,
+            2 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+}
+
+type Logger = Box<dyn Fn(Level, fmt::Arguments) + Send + Sync>;
+
+// Most verbose level emitted; warnings and errors by default
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+pub fn set_max_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn max_level() -> Level {
+    Level::from_u8(MAX_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= max_level()
+}
+
+// Send messages to `logger` instead of stderr. Only the first call takes
+// effect; returns false if a logger was already installed.
+pub fn set_logger(logger: impl Fn(Level, fmt::Arguments) + Send + Sync + 'static) -> bool {
+    LOGGER.set(Box::new(logger)).is_ok()
+}
+
+// Emit a message at `level`; used through the `error!`, `warn!`, `info!` and
+// `debug!` macros
+pub fn log(level: Level, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    match LOGGER.get() {
+        Some(logger) => logger(level, args),
+        None => eprintln!("{}: {}", level.name(), args),
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Debug, format_args!($($arg)*))
+    };
+}
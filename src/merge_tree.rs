@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+// History of the merges performed by the union-find sweep, 12 bytes per
+// cell. `link` keeps the set each absorbed set was attached to (never
+// path-compressed) and `merged_by` the cell whose activation made that
+// merge, so set membership at any past moment can be recovered by following
+// links. Cells are stored as u32, which grids of at most `MAX_CELLS` allow.
+pub struct MergeTree {
+    pub rows: usize,
+    pub cols: usize,
+    link: Vec<u32>,
+    merged_by: Vec<u32>,
+    // Union-find root -> node standing for its set in `link`; only needed
+    // during the sweep and freed by `finish`
+    node: Vec<u32>,
+    // Peak cell index -> node of its set when it merged into higher ground
+    pub(crate) islands: HashMap<usize, usize>,
+    // Highest point of each cell's set right after the cell was activated
+    summit: Vec<u32>,
+    // Summit -> summit of the set it was absorbed into
+    pub(crate) summit_parent: HashMap<usize, usize>,
+}
+
+// Stands for no cell in the u32 vectors
+const NONE: u32 = u32::MAX;
+
+impl MergeTree {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let size = rows * cols;
+        MergeTree {
+            rows,
+            cols,
+            link: (0..size as u32).collect(),
+            merged_by: vec![NONE; size],
+            node: (0..size as u32).collect(),
+            islands: HashMap::new(),
+            summit: vec![NONE; size],
+            summit_parent: HashMap::new(),
+        }
+    }
+
+    // Number of cells, and of nodes
+    pub(crate) fn len(&self) -> usize {
+        self.link.len()
+    }
+
+    // Node the set of `node` was attached to, or `node` itself if it never was
+    pub(crate) fn link(&self, node: usize) -> usize {
+        self.link[node] as usize
+    }
+
+    // Cell whose activation attached the set of `node`, which was attached
+    pub(crate) fn merged_by(&self, node: usize) -> usize {
+        self.merged_by[node] as usize
+    }
+
+    // Summit of the set `cell` joined when activated, None if it never was
+    pub(crate) fn summit(&self, cell: usize) -> Option<usize> {
+        let summit = self.summit[cell];
+        (summit != NONE).then_some(summit as usize)
+    }
+
+    // Record that the set of union-find root `lower` (the one with the lower
+    // summit) was absorbed into that of root `higher`, the merged set now
+    // being rooted at `root`, when cell `col` was activated. Links always run
+    // from the lower summit's set to the higher one's, whichever root the
+    // union-find keeps; returns the node standing for the absorbed set.
+    pub fn record_merge(&mut self, lower: usize, higher: usize, root: usize, col: usize) -> usize {
+        let (lower, higher) = (self.node[lower], self.node[higher]);
+        self.link[lower as usize] = higher;
+        self.merged_by[lower as usize] = col as u32;
+        self.node[root] = higher;
+        lower as usize
+    }
+
+    // Record that the summit `lower` lost its set to the higher summit `higher`
+    pub fn record_summit_merge(&mut self, lower: usize, higher: usize) {
+        if lower != higher {
+            self.summit_parent.insert(lower, higher);
+        }
+    }
+
+    // Record the summit of the set a cell joined when it was activated
+    pub fn record_activation(&mut self, cell: usize, summit: usize) {
+        self.summit[cell] = summit as u32;
+    }
+
+    // Record that the set still rooted at `root` when the sweep ends, cut off
+    // from higher ground, is the island of the peak at `peak_index`
+    pub fn record_final_island(&mut self, peak_index: usize, root: usize) {
+        self.islands.insert(peak_index, self.node[root] as usize);
+    }
+
+    // Record that the set rooted at `root` is the island of the peak at `peak_index`
+    pub fn record_island(&mut self, peak_index: usize, root: usize) {
+        self.islands.insert(peak_index, root);
+    }
+
+    // End of the sweep: drop what only the sweep needed
+    pub(crate) fn finish(&mut self) {
+        self.node = Vec::new();
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::{self, Write};
+
+use crate::export;
+use crate::georef::Georef;
+use crate::prominence::Peak;
+use crate::window::Window;
+
+// Result formats selectable with --format
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Csv,
+    Json,
+    GeoJson,
+    Kml,
+    Gpx,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Some(OutputFormat::Text),
+            "csv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::Json),
+            "geojson" => Some(OutputFormat::GeoJson),
+            "kml" => Some(OutputFormat::Kml),
+            "gpx" => Some(OutputFormat::Gpx),
+            _ => None,
+        }
+    }
+
+    // File extension of results in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::GeoJson => "geojson",
+            OutputFormat::Kml => "kml",
+            OutputFormat::Gpx => "gpx",
+        }
+    }
+}
+
+// Metadata describing the run that produced a peak list
+pub struct RunInfo<'a> {
+    pub input: &'a str,
+    pub rows: usize,
+    pub cols: usize,
+    pub max_peaks: Option<usize>,
+    pub min_prominence: i32,
+    pub georef: Option<Georef>,
+    // Part of the input processed, if cropped; peaks are still given in the
+    // input's frame, with an edge flag
+    pub window: Option<Window>,
+}
+
+impl RunInfo<'_> {
+    // Cell coordinates are used as-is when the input has no georeference
+    fn georef(&self) -> Georef {
+        self.georef.unwrap_or(Georef::GRID)
+    }
+}
+
+pub fn write_peaks<W: Write>(
+    out: &mut W,
+    format: OutputFormat,
+    peaks: &[Peak],
+    info: &RunInfo,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => write_text(out, peaks, info.window.is_some()),
+        OutputFormat::Csv => write_csv(out, peaks, info.window.is_some()),
+        OutputFormat::Json => write_json(out, peaks, info),
+        OutputFormat::GeoJson => export::write_geojson(out, peaks, &info.georef()),
+        OutputFormat::Kml => export::write_kml(out, peaks, &info.georef(), info.input),
+        OutputFormat::Gpx => export::write_gpx(out, peaks, &info.georef(), info.input),
+    }
+}
+
+// Fixed-width table, missing cols printed as NA. Cropped runs add an `edge`
+// column marking peaks whose key col may lie outside the window.
+fn write_text<W: Write>(out: &mut W, peaks: &[Peak], cropped: bool) -> io::Result<()> {
+    let edge_header = if cropped { "   edge" } else { "" };
+    writeln!(out, "Peaks by prominence:")?;
+    writeln!(
+        out,
+        "  prom    row    col   elev   crow   ccol  celev{}",
+        edge_header
+    )?;
+    writeln!(out, "{}", "-".repeat(50 + edge_header.len()))?;
+    for peak in peaks {
+        let crow = peak.col_x.map_or("NA".to_string(), |x| format!("{:>4}", x));
+        let ccol = peak.col_y.map_or("NA".to_string(), |y| format!("{:>4}", y));
+        let celev = peak
+            .col_elevation
+            .map_or("NA".to_string(), |e| format!("{:>4}", e));
+        write!(
+            out,
+            "{:>6} {:>6} {:>6} {:>6} {:>6} {:>6} {:>6}",
+            peak.prominence, peak.peak_x, peak.peak_y, peak.peak_elevation, crow, ccol, celev
+        )?;
+        if cropped {
+            write!(out, " {:>6}", if peak.edge_affected { "yes" } else { "no" })?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// CSV with a header row, missing cols left as empty fields. Cropped runs add
+// an `edge_affected` column.
+fn write_csv<W: Write>(out: &mut W, peaks: &[Peak], cropped: bool) -> io::Result<()> {
+    writeln!(
+        out,
+        "prominence,row,col,elevation,col_row,col_col,col_elevation{}",
+        if cropped { ",edge_affected" } else { "" }
+    )?;
+    for peak in peaks {
+        write!(
+            out,
+            "{},{},{},{},{},{},{}",
+            peak.prominence,
+            peak.peak_x,
+            peak.peak_y,
+            peak.peak_elevation,
+            optional(peak.col_x, ""),
+            optional(peak.col_y, ""),
+            optional(peak.col_elevation, ""),
+        )?;
+        if cropped {
+            write!(out, ",{}", peak.edge_affected)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// JSON document with run metadata and an array of peak objects
+fn write_json<W: Write>(out: &mut W, peaks: &[Peak], info: &RunInfo) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"input\": {},", json_string(info.input))?;
+    writeln!(out, "  \"rows\": {},", info.rows)?;
+    writeln!(out, "  \"cols\": {},", info.cols)?;
+    match &info.georef {
+        Some(g) => writeln!(
+            out,
+            "  \"georef\": {{\"west\": {}, \"north\": {}, \"cell_width\": {}, \"cell_height\": {}}},",
+            g.west, g.north, g.cell_width, g.cell_height
+        )?,
+        None => writeln!(out, "  \"georef\": null,")?,
+    }
+    if let Some(w) = &info.window {
+        writeln!(
+            out,
+            "  \"window\": {{\"row\": {}, \"col\": {}, \"rows\": {}, \"cols\": {}}},",
+            w.row, w.col, w.rows, w.cols
+        )?;
+    }
+    writeln!(out, "  \"parameters\": {{")?;
+    writeln!(
+        out,
+        "    \"max_peaks\": {},",
+        optional(info.max_peaks, "null")
+    )?;
+    writeln!(out, "    \"min_prominence\": {}", info.min_prominence)?;
+    writeln!(out, "  }},")?;
+    writeln!(out, "  \"peaks\": [")?;
+    for (i, peak) in peaks.iter().enumerate() {
+        let separator = if i + 1 < peaks.len() { "," } else { "" };
+        let edge = if info.window.is_some() {
+            format!(", \"edge_affected\": {}", peak.edge_affected)
+        } else {
+            String::new()
+        };
+        writeln!(
+            out,
+            "    {{\"prominence\": {}, \"row\": {}, \"col\": {}, \"elevation\": {}, \
+             \"col_row\": {}, \"col_col\": {}, \"col_elevation\": {}{}}}{}",
+            peak.prominence,
+            peak.peak_x,
+            peak.peak_y,
+            peak.peak_elevation,
+            optional(peak.col_x, "null"),
+            optional(peak.col_y, "null"),
+            optional(peak.col_elevation, "null"),
+            edge,
+            separator
+        )?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+fn optional<T: ToString>(value: Option<T>, missing: &str) -> String {
+    value.map_or(missing.to_string(), |v| v.to_string())
+}
+
+// Quote and escape a string for inclusion in JSON
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
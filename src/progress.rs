@@ -0,0 +1,87 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+// Stages of a run reported to progress callbacks
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Phase {
+    Read,
+    PeakDetection,
+    Sort,
+    Sweep,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Read => "read",
+            Phase::PeakDetection => "peak detection",
+            Phase::Sort => "sort",
+            Phase::Sweep => "sweep",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Shared flag a host application sets to abort a run. Clones share the flag,
+// so one clone can be handed to the run while another is cancelled from
+// a different thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Progress callback and cancellation token observed by long-running steps.
+// The callback receives the phase, cells processed so far and the phase
+// total; it is called at coarse intervals, always including 0 and the total.
+#[derive(Clone, Copy, Default)]
+pub struct Monitor<'a> {
+    progress: Option<&'a dyn Fn(Phase, usize, usize)>,
+    cancel: Option<&'a CancelToken>,
+}
+
+impl<'a> Monitor<'a> {
+    pub fn new() -> Monitor<'a> {
+        Monitor::default()
+    }
+
+    pub fn progress(mut self, callback: &'a dyn Fn(Phase, usize, usize)) -> Monitor<'a> {
+        self.progress = Some(callback);
+        self
+    }
+
+    pub fn cancel_token(mut self, token: &'a CancelToken) -> Monitor<'a> {
+        self.cancel = Some(token);
+        self
+    }
+
+    // Report progress, then fail with `Error::Cancelled` if the run was aborted
+    pub fn step(&self, phase: Phase, processed: usize, total: usize) -> Result<()> {
+        if let Some(callback) = self.progress {
+            callback(phase, processed, total);
+        }
+        if self.cancel.is_some_and(CancelToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,612 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::ops::ControlFlow;
+
+use crate::error::Result;
+use crate::grid::{cell_count, Elevations};
+use crate::merge_tree::MergeTree;
+use crate::progress::{Monitor, Phase};
+use crate::region::{BitSet, Mask};
+use crate::window::Edges;
+
+// Number of peaks reported by default, ordered by prominence
+pub const DEFAULT_MAX_PEAKS: usize = 100;
+
+// Cells processed between progress reports
+const REPORT_INTERVAL: usize = 1 << 16;
+
+// Offsets of the eight neighbours of a cell
+pub(crate) const NEIGHBOURS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// Structure to represent a peak's prominence output.
+// `peak_x`/`col_x` are rows and `peak_y`/`col_y` columns; the highest peak
+// has no key col.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Peak {
+    pub prominence: i32,
+    pub peak_x: usize,
+    pub peak_y: usize,
+    pub peak_elevation: i32,
+    pub col_x: Option<usize>,
+    pub col_y: Option<usize>,
+    pub col_elevation: Option<i32>,
+    // The peak's island reaches an open edge of the grid (see
+    // `ProminenceOptions::open_edges`), so its key col may lie beyond it
+    pub edge_affected: bool,
+}
+
+// Heap entry ordering peaks by prominence only
+struct HeapPeak(Peak);
+
+impl Ord for HeapPeak {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.prominence.cmp(&self.0.prominence) // Max-heap
+    }
+}
+
+impl PartialOrd for HeapPeak {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for HeapPeak {}
+impl PartialEq for HeapPeak {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.prominence == other.0.prominence
+    }
+}
+
+// Parameters of a prominence computation
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProminenceOptions {
+    max_peaks: Option<usize>,
+    min_prominence: i32,
+    track_merges: bool,
+    open_edges: Edges,
+    within: Option<Mask>,
+}
+
+impl Default for ProminenceOptions {
+    fn default() -> Self {
+        ProminenceOptions {
+            max_peaks: Some(DEFAULT_MAX_PEAKS),
+            min_prominence: 0,
+            track_merges: false,
+            open_edges: Edges::default(),
+            within: None,
+        }
+    }
+}
+
+impl ProminenceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Report at most `n` peaks, ordered by prominence
+    pub fn max_peaks(mut self, n: usize) -> Self {
+        self.max_peaks = Some(n);
+        self
+    }
+
+    // Report every peak
+    pub fn unlimited(mut self) -> Self {
+        self.max_peaks = None;
+        self
+    }
+
+    // Drop peaks with less prominence than `metres`
+    pub fn min_prominence(mut self, metres: i32) -> Self {
+        self.min_prominence = metres;
+        self
+    }
+
+    // Keep the merge history needed for island and domain products
+    pub fn track_merges(mut self, enabled: bool) -> Self {
+        self.track_merges = enabled;
+        self
+    }
+
+    // Grid sides cut out of larger terrain; peaks whose island reaches one
+    // are flagged `edge_affected`
+    pub fn open_edges(mut self, edges: Edges) -> Self {
+        self.open_edges = edges;
+        self
+    }
+
+    // Treat cells outside `mask` as beyond the edge of the world: they are
+    // not swept and no col lies among them. The highest summit of each part
+    // of the mask cut off from the rest takes its elevation as prominence.
+    pub fn within(mut self, mask: Mask) -> Self {
+        self.within = Some(mask);
+        self
+    }
+
+    pub fn get_max_peaks(&self) -> Option<usize> {
+        self.max_peaks
+    }
+
+    pub fn get_min_prominence(&self) -> i32 {
+        self.min_prominence
+    }
+}
+
+// Output of `compute_prominence`
+pub struct Prominence {
+    // Peaks ordered by descending prominence
+    pub peaks: Vec<Peak>,
+    // Present when requested with `ProminenceOptions::track_merges`
+    pub merges: Option<MergeTree>,
+}
+
+// Union-Find structure over cell indices, 9 bytes per cell
+struct UnionFind {
+    parent: Vec<u32>,
+    rank: Vec<u8>,
+    // Highest cell of each set, kept at its root
+    summit: Vec<u32>,
+    // Roots of sets reaching an open edge, when there are open edges
+    edge: Option<BitSet>,
+    // Merge history, kept only when island or domain products are requested
+    merges: Option<MergeTree>,
+}
+
+impl UnionFind {
+    fn new(size: usize, open_edges: bool, merges: Option<MergeTree>) -> Self {
+        UnionFind {
+            parent: (0..size as u32).collect(),
+            rank: vec![0; size],
+            summit: (0..size as u32).collect(),
+            edge: open_edges.then(|| BitSet::new(size)),
+            merges,
+        }
+    }
+
+    // The merge history once the sweep stops
+    fn into_merges(self) -> Option<MergeTree> {
+        self.merges.map(|mut merges| {
+            merges.finish();
+            merges
+        })
+    }
+
+    // Root of `x`, halving the path on the way
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] as usize != x {
+            let grandparent = self.parent[self.parent[x] as usize];
+            self.parent[x] = grandparent;
+            x = grandparent as usize;
+        }
+        x
+    }
+
+    fn union<G: Elevations + ?Sized>(
+        &mut self,
+        grid: &G,
+        x: usize,
+        y: usize,
+        col: usize,
+        peaks: &mut Vec<(Peak, Option<usize>)>,
+        peaks_set: &BitSet,
+    ) {
+        let root_x = self.find(x);
+        let root_y = self.find(y);
+        if root_x == root_y {
+            return;
+        }
+
+        if self.rank[root_x] < self.rank[root_y] {
+            self.merge(grid, root_x, root_y, col, peaks, peaks_set);
+        } else if self.rank[root_x] > self.rank[root_y] {
+            self.merge(grid, root_y, root_x, col, peaks, peaks_set);
+        } else {
+            self.merge(grid, root_y, root_x, col, peaks, peaks_set);
+            self.rank[root_x] += 1;
+        }
+    }
+
+    // Attach root `smaller` to root `larger`. Of the two summits, the lower
+    // one finishes here with `col` as its key col, whichever set it
+    // belongs to, and the higher one as its parent.
+    fn merge<G: Elevations + ?Sized>(
+        &mut self,
+        grid: &G,
+        smaller: usize,
+        larger: usize,
+        col: usize,
+        peaks: &mut Vec<(Peak, Option<usize>)>,
+        peaks_set: &BitSet,
+    ) {
+        self.parent[smaller] = larger as u32;
+
+        let (lower_root, higher_root) = if outranks(
+            grid,
+            self.summit[smaller] as usize,
+            self.summit[larger] as usize,
+        ) {
+            (larger, smaller)
+        } else {
+            (smaller, larger)
+        };
+        let lower = self.summit[lower_root] as usize;
+        let higher = self.summit[higher_root] as usize;
+        self.summit[larger] = higher as u32;
+
+        let mut edge_affected = false;
+        if let Some(edge) = &mut self.edge {
+            edge_affected = edge.contains(lower_root);
+            if edge.contains(smaller) {
+                edge.insert(larger);
+            }
+        }
+
+        let island = self
+            .merges
+            .as_mut()
+            .map(|merges| merges.record_merge(lower_root, higher_root, larger, col));
+        if let Some(merges) = &mut self.merges {
+            merges.record_summit_merge(lower, higher);
+        }
+
+        // Only compute prominence if the lower summit is a peak above sea level
+        let elevation = grid.metres(lower);
+        if peaks_set.contains(lower) && elevation > 0 {
+            let col_elevation = grid.metres(col);
+            let prominence = elevation - col_elevation;
+            if prominence > 0 {
+                let cols = grid.dimensions().1;
+                let peak = Peak {
+                    prominence,
+                    peak_x: lower / cols,
+                    peak_y: lower % cols,
+                    peak_elevation: elevation,
+                    col_x: Some(col / cols),
+                    col_y: Some(col % cols),
+                    col_elevation: Some(col_elevation),
+                    edge_affected,
+                };
+                peaks.push((peak, Some(higher)));
+                if let (Some(merges), Some(island)) = (&mut self.merges, island) {
+                    merges.record_island(lower, island);
+                }
+            }
+        }
+    }
+}
+
+// Whether cell `a` ranks above `b`: higher, or as high and swept earlier
+pub(crate) fn outranks<G: Elevations + ?Sized>(grid: &G, a: usize, b: usize) -> bool {
+    let (key_a, key_b) = (grid.key(a), grid.key(b));
+    key_a > key_b || (key_a == key_b && a < b)
+}
+
+// Cells in sweep order: descending elevation, ties in row-major order. Keys
+// spanning a narrow range, as integer DEMs do, are counting-sorted.
+fn sweep_order<G: Elevations + ?Sized>(grid: &G, total: usize) -> Vec<u32> {
+    let (low, high) = (0..total).fold((u32::MAX, 0), |(low, high), i| {
+        let key = grid.key(i);
+        (low.min(key), high.max(key))
+    });
+    let mut order = vec![0u32; total];
+    if total == 0 {
+        return order;
+    }
+    let span = (high - low) as usize + 1;
+    if span <= total.max(1 << 16) {
+        // First slot of each key, highest keys first
+        let mut next = vec![0u32; span];
+        for i in 0..total {
+            next[(grid.key(i) - low) as usize] += 1;
+        }
+        let mut start = 0;
+        for slot in next.iter_mut().rev() {
+            let count = *slot;
+            *slot = start;
+            start += count;
+        }
+        for i in 0..total {
+            let slot = &mut next[(grid.key(i) - low) as usize];
+            order[*slot as usize] = i as u32;
+            *slot += 1;
+        }
+    } else {
+        for (i, slot) in order.iter_mut().enumerate() {
+            *slot = i as u32;
+        }
+        order.sort_unstable_by_key(|&i| (Reverse(grid.key(i as usize)), i));
+    }
+    order
+}
+
+// Compute prominence using Union-Find with flat grid
+pub fn compute_prominence<G: Elevations + ?Sized>(
+    grid: &G,
+    options: &ProminenceOptions,
+) -> Prominence {
+    uncancellable(compute_prominence_with(grid, options, &Monitor::new()))
+}
+
+// `compute_prominence` reporting progress to `monitor`; fails with
+// `Error::Cancelled` if its cancel token is triggered
+pub fn compute_prominence_with<G: Elevations + ?Sized>(
+    grid: &G,
+    options: &ProminenceOptions,
+    monitor: &Monitor,
+) -> Result<Prominence> {
+    let mut found = Vec::new();
+    let merges = for_each_peak_with(grid, options, monitor, |peak| {
+        found.push(peak);
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(Prominence {
+        peaks: top_peaks(found, options.max_peaks),
+        merges,
+    })
+}
+
+// The `max_peaks` most prominent of `peaks`, given in sweep order, ordered
+// as `compute_prominence` orders them
+pub(crate) fn top_peaks<I: IntoIterator<Item = Peak>>(
+    peaks: I,
+    max_peaks: Option<usize>,
+) -> Vec<Peak> {
+    // Pushed one by one, as ties come out in an order depending on the heap's
+    // history
+    let mut heap = BinaryHeap::new();
+    for peak in peaks {
+        heap.push(HeapPeak(peak));
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .take(max_peaks.unwrap_or(usize::MAX))
+        .map(|HeapPeak(peak)| peak)
+        .collect()
+}
+
+// Run the sweep, handing each peak to `visit` as soon as its key col is
+// found, without collecting them. Peaks arrive by descending col elevation,
+// starting with the highest peak, rather than by prominence; with open edges
+// or a `within` mask the highest peak comes last instead, once the ground it
+// reaches is known. `min_prominence` applies but `max_peaks` does not. Returning `ControlFlow::Break` stops the
+// sweep early. The merge history, if tracked, covers the cells swept so far.
+pub fn for_each_peak<G, F>(grid: &G, options: &ProminenceOptions, visit: F) -> Option<MergeTree>
+where
+    G: Elevations + ?Sized,
+    F: FnMut(Peak) -> ControlFlow<()>,
+{
+    uncancellable(for_each_peak_with(grid, options, &Monitor::new(), visit))
+}
+
+// `for_each_peak` reporting progress to `monitor`
+pub fn for_each_peak_with<G, F>(
+    grid: &G,
+    options: &ProminenceOptions,
+    monitor: &Monitor,
+    mut visit: F,
+) -> Result<Option<MergeTree>>
+where
+    G: Elevations + ?Sized,
+    F: FnMut(Peak) -> ControlFlow<()>,
+{
+    sweep(grid, options, monitor, |peak, _| visit(peak))
+}
+
+// The sweep behind `for_each_peak_with`, also handing over each peak's
+// parent: the summit of the higher ground met at its key col, if any
+pub(crate) fn sweep<G, F>(
+    grid: &G,
+    options: &ProminenceOptions,
+    monitor: &Monitor,
+    mut visit: F,
+) -> Result<Option<MergeTree>>
+where
+    G: Elevations + ?Sized,
+    F: FnMut(Peak, Option<usize>) -> ControlFlow<()>,
+{
+    let (rows, cols) = grid.dimensions();
+    let total_points = cell_count(rows, cols)?;
+
+    let within = options.within.as_ref();
+    if let Some(mask) = within {
+        assert_eq!(
+            (mask.rows, mask.cols),
+            (rows, cols),
+            "mask and grid dimensions differ"
+        );
+    }
+    let inside = |index: usize| within.is_none_or(|mask| mask.contains(index));
+
+    // Step 1: Identify peaks
+    let mut peaks_set = BitSet::new(total_points);
+
+    let rows_per_report = (REPORT_INTERVAL / cols.max(1)).max(1);
+    for x in 0..rows {
+        if x % rows_per_report == 0 {
+            monitor.step(Phase::PeakDetection, x * cols, total_points)?;
+        }
+        for y in 0..cols {
+            let index = x * cols + y;
+            if !inside(index) {
+                continue;
+            }
+            let key = grid.key(index);
+
+            let mut is_peak = true;
+            for &(dx, dy) in &NEIGHBOURS {
+                let nx = x as i32 + dx;
+                let ny = y as i32 + dy;
+                if nx >= 0 && nx < rows as i32 && ny >= 0 && ny < cols as i32 {
+                    let neighbour = (nx as usize) * cols + ny as usize;
+                    if inside(neighbour) && grid.key(neighbour) >= key {
+                        is_peak = false;
+                        break;
+                    }
+                }
+            }
+            if is_peak {
+                peaks_set.insert(index);
+            }
+        }
+    }
+
+    monitor.step(Phase::PeakDetection, total_points, total_points)?;
+
+    // Step 2: Sort points descending
+    monitor.step(Phase::Sort, 0, total_points)?;
+    let order = sweep_order(grid, total_points);
+    monitor.step(Phase::Sort, total_points, total_points)?;
+
+    // Step 3: Union-Find
+    let merges = options.track_merges.then(|| MergeTree::new(rows, cols));
+    let open_edges = options.open_edges;
+    let mut uf = UnionFind::new(total_points, open_edges.any(), merges);
+    let mut finalised = Vec::new();
+    let mut activated = BitSet::new(total_points);
+    let mut highest = None;
+    // Whether the highest peak waits for the end of the sweep, to learn
+    // which open edges and which parts of the mask its ground reaches
+    let defer_highest = open_edges.any() || within.is_some();
+
+    // Step 4: Process points
+    for (processed, &index) in order.iter().enumerate() {
+        if processed % REPORT_INTERVAL == 0 {
+            monitor.step(Phase::Sweep, processed, total_points)?;
+        }
+        let index = index as usize;
+        if !inside(index) {
+            continue;
+        }
+        let (x, y) = (index / cols, index % cols);
+        activated.insert(index);
+
+        // The first point swept is the highest; it has no key col
+        if highest.is_none() {
+            highest = Some(index);
+            if !defer_highest && peaks_set.contains(index) {
+                finalised.push((summit_peak(grid, cols, index, false), None));
+            }
+        }
+
+        // neighbors
+        for &(dx, dy) in &NEIGHBOURS {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx >= 0 && nx < rows as i32 && ny >= 0 && ny < cols as i32 {
+                let neighbor_index = (nx as usize) * cols + ny as usize;
+                if activated.contains(neighbor_index) {
+                    uf.union(
+                        grid,
+                        index,
+                        neighbor_index,
+                        index,
+                        &mut finalised,
+                        &peaks_set,
+                    );
+                }
+            }
+        }
+
+        // Only after the unions, so that a col on the edge does not count
+        // as part of the islands it joins
+        if open_edges.contains(x, y, rows, cols) {
+            let root = uf.find(index);
+            if let Some(edge) = &mut uf.edge {
+                edge.insert(root);
+            }
+        }
+
+        if uf.merges.is_some() {
+            let root = uf.find(index);
+            let summit = uf.summit[root] as usize;
+            if let Some(merges) = &mut uf.merges {
+                merges.record_activation(index, summit);
+            }
+        }
+
+        for (peak, parent) in finalised.drain(..) {
+            if peak.prominence >= options.min_prominence && visit(peak, parent).is_break() {
+                return Ok(uf.into_merges());
+            }
+        }
+    }
+
+    // A deferred highest peak is only complete now: its ground reaches an
+    // open edge if any of the cells joined to it do
+    let main = highest.map(|cell| uf.find(cell));
+    if let Some((summit, root)) = highest.zip(main).filter(|_| defer_highest) {
+        let edge_affected = uf.edge.as_ref().is_some_and(|edge| edge.contains(root));
+        let peak = summit_peak(grid, cols, summit, edge_affected);
+        if peaks_set.contains(summit)
+            && peak.prominence >= options.min_prominence
+            && visit(peak, None).is_break()
+        {
+            return Ok(uf.into_merges());
+        }
+    }
+
+    // Parts of a mask cut off from the highest summit's part never merge
+    // into it; their own highest summits have no key col
+    if within.is_some() {
+        for root in 0..total_points {
+            if uf.parent[root] as usize != root || !activated.contains(root) || Some(root) == main {
+                continue;
+            }
+            let summit = uf.summit[root] as usize;
+            let elevation = grid.metres(summit);
+            if !peaks_set.contains(summit) || elevation <= 0 {
+                continue;
+            }
+            if let Some(merges) = &mut uf.merges {
+                merges.record_final_island(summit, root);
+            }
+            let edge_affected = uf.edge.as_ref().is_some_and(|edge| edge.contains(root));
+            let peak = summit_peak(grid, cols, summit, edge_affected);
+            if peak.prominence >= options.min_prominence && visit(peak, None).is_break() {
+                return Ok(uf.into_merges());
+            }
+        }
+    }
+    monitor.step(Phase::Sweep, total_points, total_points)?;
+
+    Ok(uf.into_merges())
+}
+
+// Peak at `summit` with no higher ground to reach, its elevation as prominence
+fn summit_peak<G: Elevations + ?Sized>(
+    grid: &G,
+    cols: usize,
+    summit: usize,
+    edge_affected: bool,
+) -> Peak {
+    let elevation = grid.metres(summit);
+    Peak {
+        prominence: elevation,
+        peak_x: summit / cols,
+        peak_y: summit % cols,
+        peak_elevation: elevation,
+        col_x: None,
+        col_y: None,
+        col_elevation: None,
+        edge_affected,
+    }
+}
+
+// Unwrap the result of a run without a cancel token, which cannot fail for
+// grids built through `Grid::new` or `CompactGrid::new`
+fn uncancellable<T>(result: Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => unreachable!("uncancellable run failed: {}", error),
+    }
+}
@@ -0,0 +1,426 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::georef::Georef;
+
+// How raster values should be presented where a format has a choice
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RasterKind {
+    // Peak IDs: written with a colour palette where supported
+    Labels,
+    // Measurements such as prominence in metres
+    Values,
+}
+
+// Marker for cells without data in the computed products' ASCII grids
+pub const NO_DATA: i32 = -9999;
+
+// A computed raster product aligned with the input grid, or a grid's own
+// elevations. Only .asc and .csv take values other than `u32`.
+pub struct Raster<T = u32> {
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<T>,
+    pub georef: Option<Georef>,
+    pub kind: RasterKind,
+    // Value marking cells without data, where the format records one
+    pub no_data: Option<i32>,
+}
+
+// Write a raster in the format implied by the file extension:
+// .pgm, .png, .tif/.tiff, .asc or .csv
+pub fn write_raster(path: &str, raster: &Raster) -> Result<()> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let writer: fn(&mut BufWriter<File>, &Raster) -> io::Result<()> = match extension.as_deref() {
+        Some("pgm") => write_pgm,
+        Some("png") => write_png,
+        Some("tif") | Some("tiff") => write_geotiff,
+        Some("asc") => write_ascii_grid,
+        Some("csv") => write_csv,
+        _ => {
+            return Err(Error::UnsupportedFormat(format!(
+                "'{}': unsupported raster extension. Use .pgm, .png, .tif, .asc or .csv",
+                path
+            )))
+        }
+    };
+    let mut out = BufWriter::new(File::create(path).map_err(|e| Error::io(path, e))?);
+    writer(&mut out, raster)
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::io(path, e))
+}
+
+// One grid row per line, comma separated
+pub fn write_csv<W: Write, T: Display>(out: &mut W, raster: &Raster<T>) -> io::Result<()> {
+    write_rows(out, raster, ",")
+}
+
+// Binary 16-bit PGM (P5); values above 65535 are clamped
+pub fn write_pgm<W: Write>(out: &mut W, raster: &Raster) -> io::Result<()> {
+    write!(out, "P5\n{} {}\n65535\n", raster.cols, raster.rows)?;
+    let mut bytes = Vec::with_capacity(raster.values.len() * 2);
+    for &v in &raster.values {
+        bytes.extend_from_slice(&(v.min(u16::MAX as u32) as u16).to_be_bytes());
+    }
+    out.write_all(&bytes)
+}
+
+// ESRI ASCII grid with the lower-left corner taken from the georeference
+pub fn write_ascii_grid<W: Write, T: Display>(out: &mut W, raster: &Raster<T>) -> io::Result<()> {
+    let georef = raster.georef.unwrap_or(Georef::GRID);
+    let south = georef.north - raster.rows as f64 * georef.cell_height;
+    writeln!(out, "ncols {}", raster.cols)?;
+    writeln!(out, "nrows {}", raster.rows)?;
+    writeln!(out, "xllcorner {}", georef.west)?;
+    writeln!(out, "yllcorner {}", south)?;
+    if georef.cell_width == georef.cell_height {
+        writeln!(out, "cellsize {}", georef.cell_width)?;
+    } else {
+        writeln!(out, "dx {}", georef.cell_width)?;
+        writeln!(out, "dy {}", georef.cell_height)?;
+    }
+    if let Some(no_data) = raster.no_data {
+        writeln!(out, "NODATA_value {}", no_data)?;
+    }
+    write_rows(out, raster, " ")
+}
+
+fn write_rows<W: Write, T: Display>(
+    out: &mut W,
+    raster: &Raster<T>,
+    separator: &str,
+) -> io::Result<()> {
+    for row in raster.values.chunks(raster.cols) {
+        let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(out, "{}", line.join(separator))?;
+    }
+    Ok(())
+}
+
+// PNG: label rasters as an 8-bit palette image (IDs wrap every 255 colours),
+// value rasters as 16-bit grayscale holding the values themselves
+pub fn write_png<W: Write>(out: &mut W, raster: &Raster) -> io::Result<()> {
+    match raster.kind {
+        RasterKind::Labels => {
+            let pixels: Vec<u8> = raster
+                .values
+                .iter()
+                .map(|&v| if v == 0 { 0 } else { ((v - 1) % 255 + 1) as u8 })
+                .collect();
+            let mut palette = Vec::with_capacity(256 * 3);
+            for i in 0..256u32 {
+                palette.extend_from_slice(&label_color(i));
+            }
+            write_png_image(
+                out,
+                raster.cols,
+                raster.rows,
+                PngPixels::Palette(&pixels, &palette),
+            )
+        }
+        RasterKind::Values => {
+            let pixels: Vec<u16> = raster
+                .values
+                .iter()
+                .map(|&v| v.min(u16::MAX as u32) as u16)
+                .collect();
+            write_png_image(out, raster.cols, raster.rows, PngPixels::Gray16(&pixels))
+        }
+    }
+}
+
+// Distinct, reproducible colour for a label; 0 is black
+fn label_color(label: u32) -> [u8; 3] {
+    if label == 0 {
+        return [0, 0, 0];
+    }
+    // Golden-ratio hue steps keep neighbouring IDs apart
+    let hue = (label as f64 * 0.618_033_988_75).fract() * 6.0;
+    let f = hue.fract();
+    let (hi, lo) = (230.0, 70.0);
+    let up = lo + (hi - lo) * f;
+    let down = hi - (hi - lo) * f;
+    let (r, g, b) = match hue as u32 {
+        0 => (hi, up, lo),
+        1 => (down, hi, lo),
+        2 => (lo, hi, up),
+        3 => (lo, down, hi),
+        4 => (up, lo, hi),
+        _ => (hi, lo, down),
+    };
+    [r as u8, g as u8, b as u8]
+}
+
+// Pixel layouts supported by the PNG encoder
+pub enum PngPixels<'a> {
+    Gray16(&'a [u16]),
+    Palette(&'a [u8], &'a [u8]),
+    Rgb(&'a [u8]),
+}
+
+pub fn write_png_image<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    pixels: PngPixels,
+) -> io::Result<()> {
+    let (bit_depth, color_type, bytes_per_pixel) = match pixels {
+        PngPixels::Gray16(_) => (16u8, 0u8, 2),
+        PngPixels::Palette(_, _) => (8, 3, 1),
+        PngPixels::Rgb(_) => (8, 2, 3),
+    };
+    let samples: Vec<u8> = match pixels {
+        PngPixels::Gray16(values) => values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+        PngPixels::Palette(indices, _) => indices.to_vec(),
+        PngPixels::Rgb(rgb) => rgb.to_vec(),
+    };
+
+    // Every scanline uses the Up filter so repeated rows become runs of zeros
+    let stride = width * bytes_per_pixel;
+    let mut filtered = Vec::with_capacity((stride + 1) * height);
+    for row in 0..height {
+        filtered.push(2);
+        let line = &samples[row * stride..(row + 1) * stride];
+        if row == 0 {
+            filtered.extend_from_slice(line);
+        } else {
+            let above = &samples[(row - 1) * stride..row * stride];
+            filtered.extend(line.iter().zip(above).map(|(a, b)| a.wrapping_sub(*b)));
+        }
+    }
+
+    out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+    write_png_chunk(out, b"IHDR", &header)?;
+    if let PngPixels::Palette(_, palette) = pixels {
+        write_png_chunk(out, b"PLTE", palette)?;
+    }
+    write_png_chunk(out, b"IDAT", &zlib_compress(&filtered))?;
+    write_png_chunk(out, b"IEND", &[])
+}
+
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let mut crc = crc32_update(0xffff_ffff, kind);
+    crc = crc32_update(crc, data);
+    out.write_all(&(crc ^ 0xffff_ffff).to_be_bytes())
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+// zlib stream using a single fixed-Huffman deflate block; only runs of
+// repeated bytes are matched, which is what label and filtered rasters contain
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    const LENGTH_BASE: [usize; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const LENGTH_EXTRA: [u32; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+
+    let mut bits = BitWriter::default();
+    bits.bytes.extend_from_slice(&[0x78, 0x01]);
+    bits.write(1, 1); // final block
+    bits.write(1, 2); // fixed Huffman codes
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut run = 0;
+        if i > 0 {
+            while run < 258 && i + run < data.len() && data[i + run] == data[i - 1] {
+                run += 1;
+            }
+        }
+        if run >= 3 {
+            let code = LENGTH_BASE
+                .iter()
+                .rposition(|&base| base <= run)
+                .unwrap_or(0);
+            bits.write_fixed_symbol(257 + code as u32);
+            bits.write((run - LENGTH_BASE[code]) as u32, LENGTH_EXTRA[code]);
+            bits.write_huffman(0, 5); // distance 1
+            i += run;
+        } else {
+            bits.write_fixed_symbol(data[i] as u32);
+            i += 1;
+        }
+    }
+    bits.write_fixed_symbol(256);
+    let mut bytes = bits.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    // Append `count` bits of `value`, least significant first
+    fn write(&mut self, value: u32, count: u32) {
+        for bit in 0..count {
+            self.current |= ((value >> bit) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    // Huffman codes are packed most significant bit first
+    fn write_huffman(&mut self, code: u32, length: u32) {
+        for bit in (0..length).rev() {
+            self.write((code >> bit) & 1, 1);
+        }
+    }
+
+    fn write_fixed_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_huffman(0x30 + symbol, 8),
+            144..=255 => self.write_huffman(0x190 + symbol - 144, 9),
+            256..=279 => self.write_huffman(symbol - 256, 7),
+            _ => self.write_huffman(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+// Baseline little-endian TIFF with 32-bit unsigned samples in a single strip,
+// plus GeoTIFF tags (WGS84 geographic, pixel-is-area) when georeferenced
+pub fn write_geotiff<W: Write>(out: &mut W, raster: &Raster) -> io::Result<()> {
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const DOUBLE: u16 = 12;
+
+    let width = raster.cols as u32;
+    let height = raster.rows as u32;
+    let image_bytes = raster.values.len() as u32 * 4;
+
+    // (tag, type, count, payload)
+    let mut entries: Vec<(u16, u16, u32, Vec<u8>)> = vec![
+        (256, LONG, 1, width.to_le_bytes().to_vec()),
+        (257, LONG, 1, height.to_le_bytes().to_vec()),
+        (258, SHORT, 1, 32u16.to_le_bytes().to_vec()),
+        (259, SHORT, 1, 1u16.to_le_bytes().to_vec()),
+        (262, SHORT, 1, 1u16.to_le_bytes().to_vec()),
+        (273, LONG, 1, Vec::new()), // strip offset, filled in below
+        (277, SHORT, 1, 1u16.to_le_bytes().to_vec()),
+        (278, LONG, 1, height.to_le_bytes().to_vec()),
+        (279, LONG, 1, image_bytes.to_le_bytes().to_vec()),
+        (284, SHORT, 1, 1u16.to_le_bytes().to_vec()),
+        (339, SHORT, 1, 1u16.to_le_bytes().to_vec()),
+    ];
+    if let Some(g) = &raster.georef {
+        let doubles = |values: &[f64]| values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let shorts = |values: &[u16]| values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        entries.push((
+            33550,
+            DOUBLE,
+            3,
+            doubles(&[g.cell_width, g.cell_height, 0.0]),
+        ));
+        entries.push((
+            33922,
+            DOUBLE,
+            6,
+            doubles(&[0.0, 0.0, 0.0, g.west, g.north, 0.0]),
+        ));
+        let keys: [u16; 16] = [
+            1, 1, 0, 3, // version, revision, key count
+            1024, 0, 1, 2, // GTModelType: geographic
+            1025, 0, 1, 1, // GTRasterType: pixel is area
+            2048, 0, 1, 4326, // GeographicType: WGS 84
+        ];
+        entries.push((34735, SHORT, keys.len() as u32, shorts(&keys)));
+    }
+
+    // Header, IFD, then out-of-line payloads, then the image strip
+    let ifd_size = 2 + entries.len() as u32 * 12 + 4;
+    let mut extra_offset = 8 + ifd_size;
+    let mut extra = Vec::new();
+    let mut offsets = Vec::new();
+    for (_, _, _, payload) in &entries {
+        if payload.len() > 4 {
+            offsets.push(Some(extra_offset));
+            extra.extend_from_slice(payload);
+            extra_offset += payload.len() as u32;
+        } else {
+            offsets.push(None);
+        }
+    }
+    let strip_offset = extra_offset;
+
+    out.write_all(b"II*\0")?;
+    out.write_all(&8u32.to_le_bytes())?;
+    out.write_all(&(entries.len() as u16).to_le_bytes())?;
+    for ((tag, kind, count, payload), offset) in entries.iter().zip(&offsets) {
+        out.write_all(&tag.to_le_bytes())?;
+        out.write_all(&kind.to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+        let mut value = [0u8; 4];
+        if *tag == 273 {
+            value = strip_offset.to_le_bytes();
+        } else if let Some(offset) = offset {
+            value = offset.to_le_bytes();
+        } else {
+            value[..payload.len()].copy_from_slice(payload);
+        }
+        out.write_all(&value)?;
+    }
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&extra)?;
+
+    let mut bytes = Vec::with_capacity(raster.values.len() * 4);
+    for &v in &raster.values {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    out.write_all(&bytes)
+}
@@ -0,0 +1,111 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::grid::Grid;
+use crate::prominence::Peak;
+
+// Straightforward, slow prominence computation used to cross-check the
+// union-find sweep. Every peak (a cell strictly higher than its eight
+// neighbours) floods outwards, always stepping to the highest unvisited cell
+// next to the flooded area, until it reaches higher ground: a cell above it,
+// or as high and earlier in row-major order. The lowest cell flooded on the
+// way is its key col. The highest peak reaches no higher ground and takes
+// its elevation as prominence; other peaks are reported above sea level
+// only. Runs in O(n^2 log n) for n cells, so it suits small grids only.
+// Peaks are ordered by descending prominence, then elevation, then position.
+pub fn reference_prominence(grid: &Grid) -> Vec<Peak> {
+    let mut peaks = Vec::new();
+    for index in 0..grid.len() {
+        if !is_peak(grid, index) {
+            continue;
+        }
+        let (x, y) = (index / grid.cols, index % grid.cols);
+        let elevation = grid.data[index];
+        match key_col(grid, index) {
+            None => peaks.push(Peak {
+                prominence: elevation,
+                peak_x: x,
+                peak_y: y,
+                peak_elevation: elevation,
+                col_x: None,
+                col_y: None,
+                col_elevation: None,
+                edge_affected: false,
+            }),
+            Some(col) => {
+                let col_elevation = grid.data[col];
+                if elevation > 0 && elevation > col_elevation {
+                    peaks.push(Peak {
+                        prominence: elevation - col_elevation,
+                        peak_x: x,
+                        peak_y: y,
+                        peak_elevation: elevation,
+                        col_x: Some(col / grid.cols),
+                        col_y: Some(col % grid.cols),
+                        col_elevation: Some(col_elevation),
+                        edge_affected: false,
+                    });
+                }
+            }
+        }
+    }
+    peaks.sort_by_key(|p| {
+        (
+            Reverse(p.prominence),
+            Reverse(p.peak_elevation),
+            p.peak_x,
+            p.peak_y,
+        )
+    });
+    peaks
+}
+
+// Cells around `index` within the grid
+fn neighbours(grid: &Grid, index: usize) -> impl Iterator<Item = usize> + '_ {
+    let (x, y) = ((index / grid.cols) as i64, (index % grid.cols) as i64);
+    (-1..=1)
+        .flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+        .filter(move |&(nx, ny)| {
+            (nx, ny) != (x, y)
+                && nx >= 0
+                && ny >= 0
+                && nx < grid.rows as i64
+                && ny < grid.cols as i64
+        })
+        .map(move |(nx, ny)| nx as usize * grid.cols + ny as usize)
+}
+
+fn is_peak(grid: &Grid, index: usize) -> bool {
+    neighbours(grid, index).all(|n| grid.data[n] < grid.data[index])
+}
+
+// Flooding order: higher cells first, ties in row-major order
+fn rank(grid: &Grid, index: usize) -> (i32, Reverse<usize>) {
+    (grid.data[index], Reverse(index))
+}
+
+// Lowest cell on the best route from `peak` to higher ground, or None if
+// there is no higher ground
+fn key_col(grid: &Grid, peak: usize) -> Option<usize> {
+    let mut flooded = vec![false; grid.len()];
+    let mut frontier = BinaryHeap::new();
+    frontier.push((rank(grid, peak), peak));
+    flooded[peak] = true;
+    let mut lowest = peak;
+
+    while let Some((cell_rank, cell)) = frontier.pop() {
+        if cell_rank > rank(grid, peak) {
+            return Some(lowest);
+        }
+        if cell_rank < rank(grid, lowest) {
+            lowest = cell;
+        }
+        for n in neighbours(grid, cell) {
+            if !flooded[n] {
+                flooded[n] = true;
+                frontier.push((rank(grid, n), n));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,366 @@
+use std::fs;
+
+use crate::error::{Error, Result};
+use crate::georef::Georef;
+use crate::json::{self, Json};
+
+// One polygon: an outer ring followed by its holes, as (x, y) points
+pub type Polygon = Vec<Vec<(f64, f64)>>;
+
+// Area of interest such as a park or a country, in the georeference's
+// longitude/latitude (or grid units when the input has none). A point lies
+// in a polygon if it is inside an odd number of its rings, and in the region
+// if it lies in any polygon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub polygons: Vec<Polygon>,
+}
+
+impl Region {
+    pub fn new(polygons: Vec<Polygon>) -> Result<Region> {
+        check_polygons(&polygons).map_err(Error::InvalidParameter)?;
+        Ok(Region { polygons })
+    }
+
+    // Polygon, MultiPolygon, Feature, FeatureCollection or GeometryCollection
+    pub fn from_geojson(path: &str, text: &str) -> Result<Region> {
+        let mut polygons = Vec::new();
+        geojson_polygons(path, &json::parse(path, text)?, &mut polygons)?;
+        check_polygons(&polygons).map_err(|message| Error::format(path, message))?;
+        Ok(Region { polygons })
+    }
+
+    // POLYGON or MULTIPOLYGON; extra ordinates (Z, M) are ignored
+    pub fn from_wkt(path: &str, text: &str) -> Result<Region> {
+        let error = |message: &str| Error::format(path, format!("WKT: {}", message));
+        let text = text.trim();
+        let open = text.find('(').ok_or_else(|| error("missing '('"))?;
+        let keyword = text[..open].split_whitespace().next().unwrap_or("");
+        let mut parser = WktParser {
+            bytes: text.as_bytes(),
+            pos: open,
+        };
+        let tree = parser.list(0).map_err(|message| error(&message))?;
+        if parser.bytes[parser.pos..]
+            .iter()
+            .any(|b| !b.is_ascii_whitespace())
+        {
+            return Err(error("trailing characters"));
+        }
+        let polygons = match (keyword.to_ascii_uppercase().as_str(), &tree) {
+            ("POLYGON", _) => wkt_polygon(&tree).map(|polygon| vec![polygon]),
+            ("MULTIPOLYGON", Wkt::List(parts)) => parts.iter().map(wkt_polygon).collect(),
+            ("MULTIPOLYGON", Wkt::Point(..)) => None,
+            (other, _) => return Err(error(&format!("unsupported geometry '{}'", other))),
+        }
+        .ok_or_else(|| error("malformed coordinates"))?;
+        check_polygons(&polygons).map_err(|message| Error::format(path, message))?;
+        Ok(Region { polygons })
+    }
+
+    // (west, south, east, north) of all rings
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        self.polygons.iter().flatten().flatten().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(west, south, east, north), &(x, y)| {
+                (west.min(x), south.min(y), east.max(x), north.max(y))
+            },
+        )
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.polygons.iter().any(|polygon| {
+            let mut inside = false;
+            for ring in polygon {
+                for (&a, &b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                    if let Some(crossing) = crossing(a, b, y) {
+                        if x < crossing {
+                            inside = !inside;
+                        }
+                    }
+                }
+            }
+            inside
+        })
+    }
+
+    // Cells of a `rows` x `cols` grid whose centres lie in the region,
+    // filled row by row between the crossings of the polygon edges
+    pub fn mask(&self, georef: &Georef, rows: usize, cols: usize) -> Mask {
+        let mut mask = Mask::new(rows, cols);
+        let mut crossings = Vec::new();
+        for row in 0..rows {
+            let (_, y) = georef.cell_center(row, 0);
+            for polygon in &self.polygons {
+                crossings.clear();
+                for ring in polygon {
+                    for (&a, &b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                        crossings.extend(crossing(a, b, y));
+                    }
+                }
+                crossings.sort_by(f64::total_cmp);
+                // Centres x with first <= x < last, as in `contains`
+                for span in crossings.chunks_exact(2) {
+                    let column = |x: f64| {
+                        ((x - georef.west) / georef.cell_width - 0.5)
+                            .ceil()
+                            .clamp(0.0, cols as f64) as usize
+                    };
+                    for col in column(span[0])..column(span[1]) {
+                        mask.insert(row * cols + col);
+                    }
+                }
+            }
+        }
+        mask
+    }
+}
+
+fn check_polygons(polygons: &[Polygon]) -> std::result::Result<(), String> {
+    if polygons.is_empty() {
+        return Err("region has no polygons".to_string());
+    }
+    match polygons.iter().flatten().find(|ring| ring.len() < 3) {
+        Some(ring) => Err(format!("region ring of {} points", ring.len())),
+        None => Ok(()),
+    }
+}
+
+// Where edge `a`-`b` crosses the horizontal line at `y`, counting the lower
+// end of an edge but not the upper one so vertices are crossed once
+fn crossing(a: (f64, f64), b: (f64, f64), y: f64) -> Option<f64> {
+    if (a.1 > y) != (b.1 > y) {
+        Some(a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1))
+    } else {
+        None
+    }
+}
+
+// Read a GeoJSON or WKT region, told apart by the first character
+pub fn read_region(path: &str) -> Result<Region> {
+    let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    if text.trim_start().starts_with('{') {
+        Region::from_geojson(path, &text)
+    } else {
+        Region::from_wkt(path, &text)
+    }
+}
+
+fn geojson_polygons(path: &str, value: &Json, polygons: &mut Vec<Polygon>) -> Result<()> {
+    let kind = value.get("type").and_then(Json::as_str).unwrap_or("");
+    let coordinates = || {
+        value
+            .get("coordinates")
+            .ok_or_else(|| Error::format(path, format!("{} without coordinates", kind)))
+    };
+    let members = |key: &str| {
+        value
+            .get(key)
+            .and_then(Json::as_array)
+            .ok_or_else(|| Error::format(path, format!("{} without {}", kind, key)))
+    };
+    match kind {
+        "Polygon" => polygons.push(geojson_polygon(path, coordinates()?)?),
+        "MultiPolygon" => {
+            let parts = coordinates()?
+                .as_array()
+                .ok_or_else(|| Error::format(path, "MultiPolygon coordinates"))?;
+            for part in parts {
+                polygons.push(geojson_polygon(path, part)?);
+            }
+        }
+        "Feature" => match value.get("geometry") {
+            Some(geometry) if !geometry.is_null() => geojson_polygons(path, geometry, polygons)?,
+            _ => return Err(Error::format(path, "Feature without geometry")),
+        },
+        "FeatureCollection" => {
+            for feature in members("features")? {
+                geojson_polygons(path, feature, polygons)?;
+            }
+        }
+        "GeometryCollection" => {
+            for geometry in members("geometries")? {
+                geojson_polygons(path, geometry, polygons)?;
+            }
+        }
+        "" => return Err(Error::format(path, "GeoJSON object without type")),
+        other => {
+            return Err(Error::format(
+                path,
+                format!("{} has no area; expected polygons", other),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn geojson_polygon(path: &str, coordinates: &Json) -> Result<Polygon> {
+    let malformed = || Error::format(path, "malformed polygon coordinates");
+    coordinates
+        .as_array()
+        .ok_or_else(malformed)?
+        .iter()
+        .map(|ring| {
+            ring.as_array()
+                .ok_or_else(malformed)?
+                .iter()
+                .map(|point| match point.as_array() {
+                    Some([x, y, ..]) => x.as_f64().zip(y.as_f64()).ok_or_else(malformed),
+                    _ => Err(malformed()),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Parenthesised WKT coordinate lists
+enum Wkt {
+    Point(f64, f64),
+    List(Vec<Wkt>),
+}
+
+fn wkt_polygon(tree: &Wkt) -> Option<Polygon> {
+    let Wkt::List(rings) = tree else {
+        return None;
+    };
+    rings
+        .iter()
+        .map(|ring| match ring {
+            Wkt::List(points) => points
+                .iter()
+                .map(|point| match point {
+                    Wkt::Point(x, y) => Some((*x, *y)),
+                    Wkt::List(_) => None,
+                })
+                .collect(),
+            Wkt::Point(..) => None,
+        })
+        .collect()
+}
+
+// Lists a MULTIPOLYGON nests: polygons, rings and points
+const WKT_MAX_DEPTH: usize = 3;
+
+struct WktParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl WktParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    // `(` items separated by commas `)`, each a nested list or a point,
+    // inside `depth` enclosing lists
+    fn list(&mut self, depth: usize) -> std::result::Result<Wkt, String> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) != Some(&b'(') {
+            return Err(format!("expected '(' at offset {}", self.pos));
+        }
+        if depth == WKT_MAX_DEPTH {
+            return Err(format!("lists nested too deep at offset {}", self.pos));
+        }
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            items.push(if self.bytes.get(self.pos) == Some(&b'(') {
+                self.list(depth + 1)?
+            } else {
+                self.point()?
+            });
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b')') => {
+                    self.pos += 1;
+                    return Ok(Wkt::List(items));
+                }
+                _ => return Err(format!("expected ',' or ')' at offset {}", self.pos)),
+            }
+        }
+    }
+
+    // Two or more numbers separated by whitespace
+    fn point(&mut self) -> std::result::Result<Wkt, String> {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|&b| b != b',' && b != b')' && b != b'(')
+        {
+            self.pos += 1;
+        }
+        let text = String::from_utf8_lossy(&self.bytes[start..self.pos]);
+        let numbers: Vec<f64> = text
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| format!("bad coordinates '{}'", text.trim()))?;
+        match numbers[..] {
+            [x, y, ..] => Ok(Wkt::Point(x, y)),
+            _ => Err(format!("bad coordinates '{}'", text.trim())),
+        }
+    }
+}
+
+// One bit per cell of a `rows` x `cols` grid, row-major
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    pub rows: usize,
+    pub cols: usize,
+    bits: BitSet,
+}
+
+impl Mask {
+    // Mask with no cells set
+    pub fn new(rows: usize, cols: usize) -> Mask {
+        Mask {
+            rows,
+            cols,
+            bits: BitSet::new(rows * cols),
+        }
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.bits.insert(index);
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.bits.contains(index)
+    }
+
+    // Number of cells set
+    pub fn count(&self) -> usize {
+        self.bits.count()
+    }
+}
+
+// One bit per index, for masks and the sweep's per-cell flags
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BitSet(Vec<u64>);
+
+impl BitSet {
+    pub(crate) fn new(size: usize) -> Self {
+        BitSet(vec![0; size.div_ceil(64)])
+    }
+
+    pub(crate) fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        self.0[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
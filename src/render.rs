@@ -0,0 +1,429 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::georef::Georef;
+use crate::grid::Elevations;
+use crate::prominence::Peak;
+use crate::raster::{write_png_image, PngPixels};
+
+// Metres per degree of latitude, for hillshade slopes on geographic grids
+const METRES_PER_DEGREE: f64 = 111_320.0;
+
+// Assumed cell spacing when the input has no georeference (GTOPO30-like)
+const DEFAULT_CELL_METRES: f64 = 30.0 / 3600.0 * METRES_PER_DEGREE;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RenderStyle {
+    Hillshade,
+    // Hypsometric tint shaded by the hillshade
+    Tint,
+}
+
+impl RenderStyle {
+    pub fn from_name(name: &str) -> Option<RenderStyle> {
+        match name.to_ascii_lowercase().as_str() {
+            "hillshade" | "shade" => Some(RenderStyle::Hillshade),
+            "tint" | "hypsometric" => Some(RenderStyle::Tint),
+            _ => None,
+        }
+    }
+}
+
+pub struct RenderOptions {
+    pub style: RenderStyle,
+    // Longest side of the image in pixels; the grid is resampled to fit
+    pub size: usize,
+    // Number of peaks (by prominence) to mark
+    pub top: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            style: RenderStyle::Hillshade,
+            size: 1024,
+            top: 20,
+        }
+    }
+}
+
+// Render the grid with the top peaks, their key cols and peak-to-col lines.
+// The output is PNG or SVG depending on the extension of `path`.
+pub fn render_map<G: Elevations + ?Sized>(
+    path: &str,
+    grid: &G,
+    peaks: &[Peak],
+    georef: Option<&Georef>,
+    options: &RenderOptions,
+) -> Result<()> {
+    if options.size == 0 {
+        return Err(Error::InvalidParameter(
+            "render size must be positive".to_string(),
+        ));
+    }
+    let svg = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("png") => false,
+        Some(ext) if ext.eq_ignore_ascii_case("svg") => true,
+        _ => {
+            return Err(Error::UnsupportedFormat(format!(
+                "'{}': unsupported image extension. Use .png or .svg",
+                path
+            )))
+        }
+    };
+
+    let (rows, cols) = grid.dimensions();
+    let scale = options.size as f64 / rows.max(cols) as f64;
+    let width = ((cols as f64 * scale).round() as usize).max(1);
+    let height = ((rows as f64 * scale).round() as usize).max(1);
+    let elevations = resample(grid, width, height);
+
+    // Ground distance covered by one output pixel, in metres
+    let cell_metres = georef.map_or(DEFAULT_CELL_METRES, |g| g.cell_height * METRES_PER_DEGREE);
+    let pixel_metres = cell_metres * rows as f64 / height as f64;
+    let canvas = shade(&elevations, width, height, pixel_metres, options.style);
+
+    let top = &peaks[..peaks.len().min(options.top)];
+    let to_pixel = |row: usize, col: usize| {
+        (
+            (col as f64 + 0.5) * width as f64 / cols as f64,
+            (row as f64 + 0.5) * height as f64 / rows as f64,
+        )
+    };
+
+    let file = File::create(path).map_err(|e| Error::io(path, e))?;
+    write_map(
+        BufWriter::new(file),
+        svg,
+        canvas,
+        width,
+        height,
+        top,
+        to_pixel,
+    )
+    .map_err(|e| Error::io(path, e))
+}
+
+fn write_map<W: Write>(
+    mut out: W,
+    svg: bool,
+    mut canvas: Canvas,
+    width: usize,
+    height: usize,
+    top: &[Peak],
+    to_pixel: impl Fn(usize, usize) -> (f64, f64),
+) -> io::Result<()> {
+    if svg {
+        let mut png = Vec::new();
+        write_png_image(&mut png, width, height, PngPixels::Rgb(&canvas.rgb))?;
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+            width, height, width, height
+        )?;
+        writeln!(
+            out,
+            "  <image width=\"{}\" height=\"{}\" href=\"data:image/png;base64,{}\"/>",
+            width,
+            height,
+            base64(&png)
+        )?;
+        for peak in top {
+            let (px, py) = to_pixel(peak.peak_x, peak.peak_y);
+            if let (Some(crow), Some(ccol)) = (peak.col_x, peak.col_y) {
+                let (cx, cy) = to_pixel(crow, ccol);
+                writeln!(
+                    out,
+                    "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#ffd400\" stroke-width=\"1.5\"/>",
+                    px, py, cx, cy
+                )?;
+                writeln!(
+                    out,
+                    "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"6\" height=\"6\" fill=\"#1e64ff\" stroke=\"black\"/>",
+                    cx - 3.0,
+                    cy - 3.0
+                )?;
+            }
+            writeln!(
+                out,
+                "  <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#e0201c\" stroke=\"black\"/>",
+                px,
+                py - 6.0,
+                px - 5.0,
+                py + 4.0,
+                px + 5.0,
+                py + 4.0
+            )?;
+            writeln!(
+                out,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"11\" fill=\"white\" \
+                 stroke=\"black\" stroke-width=\"0.5\">{}</text>",
+                px + 7.0,
+                py - 4.0,
+                peak.prominence
+            )?;
+        }
+        writeln!(out, "</svg>")?;
+    } else {
+        for peak in top {
+            let (px, py) = to_pixel(peak.peak_x, peak.peak_y);
+            if let (Some(crow), Some(ccol)) = (peak.col_x, peak.col_y) {
+                let (cx, cy) = to_pixel(crow, ccol);
+                canvas.line(px, py, cx, cy, [255, 212, 0]);
+                canvas.square(cx, cy, 3, [30, 100, 255]);
+            }
+        }
+        for peak in top {
+            let (px, py) = to_pixel(peak.peak_x, peak.peak_y);
+            canvas.triangle(px, py, 5, [224, 32, 28]);
+            canvas.text(px + 7.0, py - 12.0, &peak.prominence.to_string());
+        }
+        write_png_image(&mut out, width, height, PngPixels::Rgb(&canvas.rgb))?;
+    }
+    out.flush()
+}
+
+// Box-average when shrinking, bilinear interpolation when enlarging
+fn resample<G: Elevations + ?Sized>(grid: &G, width: usize, height: usize) -> Vec<f64> {
+    let (rows, cols) = grid.dimensions();
+    let mut out = vec![0.0; width * height];
+    let sy = rows as f64 / height as f64;
+    let sx = cols as f64 / width as f64;
+    for py in 0..height {
+        for px in 0..width {
+            out[py * width + px] = if sx > 1.0 || sy > 1.0 {
+                let (r0, r1) = (
+                    (py as f64 * sy) as usize,
+                    (((py + 1) as f64 * sy) as usize).min(rows),
+                );
+                let (c0, c1) = (
+                    (px as f64 * sx) as usize,
+                    (((px + 1) as f64 * sx) as usize).min(cols),
+                );
+                let (r1, c1) = (r1.max(r0 + 1), c1.max(c0 + 1));
+                let mut sum = 0.0;
+                for r in r0..r1 {
+                    for c in c0..c1 {
+                        sum += grid.metres(r * cols + c) as f64;
+                    }
+                }
+                sum / ((r1 - r0) * (c1 - c0)) as f64
+            } else {
+                let y = ((py as f64 + 0.5) * sy - 0.5).clamp(0.0, (rows - 1) as f64);
+                let x = ((px as f64 + 0.5) * sx - 0.5).clamp(0.0, (cols - 1) as f64);
+                let (r0, c0) = (y as usize, x as usize);
+                let (r1, c1) = ((r0 + 1).min(rows - 1), (c0 + 1).min(cols - 1));
+                let (fy, fx) = (y - r0 as f64, x - c0 as f64);
+                let at = |r: usize, c: usize| grid.metres(r * cols + c) as f64;
+                let top = at(r0, c0) * (1.0 - fx) + at(r0, c1) * fx;
+                let bottom = at(r1, c0) * (1.0 - fx) + at(r1, c1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            };
+        }
+    }
+    out
+}
+
+// Horn hillshade, sun from the north-west at 45 degrees
+fn shade(
+    elevations: &[f64],
+    width: usize,
+    height: usize,
+    pixel_metres: f64,
+    style: RenderStyle,
+) -> Canvas {
+    let (min, max) = elevations
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &e| (lo.min(e), hi.max(e)));
+    let azimuth = 315f64.to_radians();
+    let zenith = 45f64.to_radians();
+    let at = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        elevations[y * width + x]
+    };
+
+    let mut canvas = Canvas::new(width, height);
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let dzdx = ((at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1)))
+                / (8.0 * pixel_metres);
+            let dzdy = ((at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1)))
+                / (8.0 * pixel_metres);
+            let slope = (dzdx * dzdx + dzdy * dzdy).sqrt().atan();
+            let aspect = dzdy.atan2(-dzdx);
+            let light = (zenith.cos() * slope.cos()
+                + zenith.sin()
+                    * slope.sin()
+                    * (azimuth - std::f64::consts::FRAC_PI_2 - aspect).cos())
+            .max(0.0);
+
+            let elevation = at(x, y);
+            let color = match style {
+                RenderStyle::Hillshade => [255.0; 3],
+                RenderStyle::Tint => {
+                    let t = if max > min {
+                        (elevation - min) / (max - min)
+                    } else {
+                        0.0
+                    };
+                    tint(elevation, t)
+                }
+            };
+            // Keep some ambient light so shadows stay readable
+            let brightness = 0.25 + 0.75 * light;
+            let offset = (y as usize * width + x as usize) * 3;
+            for (channel, value) in color.iter().enumerate() {
+                canvas.rgb[offset + channel] = (value * brightness).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    canvas
+}
+
+// Colour ramp from lowland green through brown to white summits; sea is blue
+fn tint(elevation: f64, t: f64) -> [f64; 3] {
+    if elevation <= 0.0 {
+        return [70.0, 120.0, 200.0];
+    }
+    const STOPS: [(f64, [f64; 3]); 5] = [
+        (0.0, [90.0, 160.0, 90.0]),
+        (0.3, [200.0, 200.0, 120.0]),
+        (0.6, [170.0, 120.0, 70.0]),
+        (0.85, [150.0, 140.0, 130.0]),
+        (1.0, [255.0, 255.0, 255.0]),
+    ];
+    for pair in STOPS.windows(2) {
+        let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+        if t <= t1 {
+            let f = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
+            return [
+                c0[0] + (c1[0] - c0[0]) * f,
+                c0[1] + (c1[1] - c0[1]) * f,
+                c0[2] + (c1[2] - c0[2]) * f,
+            ];
+        }
+    }
+    STOPS[STOPS.len() - 1].1
+}
+
+// 3x5 bitmap digits, one bit per pixel, rows top to bottom
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            rgb: vec![0; width * height * 3],
+        }
+    }
+
+    fn set(&mut self, x: isize, y: isize, color: [u8; 3]) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let offset = (y as usize * self.width + x as usize) * 3;
+            self.rgb[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: [u8; 3]) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let x = (x0 + (x1 - x0) * t).round() as isize;
+            let y = (y0 + (y1 - y0) * t).round() as isize;
+            self.set(x, y, color);
+            self.set(x + 1, y, color);
+        }
+    }
+
+    fn square(&mut self, cx: f64, cy: f64, radius: isize, color: [u8; 3]) {
+        let (cx, cy) = (cx.round() as isize, cy.round() as isize);
+        for dy in -radius - 1..=radius + 1 {
+            for dx in -radius - 1..=radius + 1 {
+                let edge = dx.abs() > radius || dy.abs() > radius;
+                self.set(cx + dx, cy + dy, if edge { [0, 0, 0] } else { color });
+            }
+        }
+    }
+
+    // Upward-pointing triangle centred on a summit, with a black outline
+    fn triangle(&mut self, cx: f64, cy: f64, radius: isize, color: [u8; 3]) {
+        let (cx, cy) = (cx.round() as isize, cy.round() as isize);
+        for (r, c) in [(radius + 1, [0, 0, 0]), (radius, color)] {
+            for dy in -r..=r {
+                let half = (dy + r) / 2;
+                for dx in -half..=half {
+                    self.set(cx + dx, cy + dy, c);
+                }
+            }
+        }
+    }
+
+    // White digits at twice the bitmap size, outlined in black
+    fn text(&mut self, x: f64, y: f64, text: &str) {
+        let (x, y) = (x.round() as isize, y.round() as isize);
+        for (pass, color) in [(0, [0, 0, 0]), (1, [255, 255, 255])] {
+            for (i, digit) in text.chars().filter_map(|c| c.to_digit(10)).enumerate() {
+                let bits = DIGITS[digit as usize];
+                for row in 0..5 {
+                    for col in 0..3 {
+                        if bits & (1 << (14 - (row * 3 + col))) == 0 {
+                            continue;
+                        }
+                        let px = x + i as isize * 8 + col as isize * 2;
+                        let py = y + row as isize * 2;
+                        let spread = if pass == 0 { -1..=2 } else { 0..=1 };
+                        for dy in spread.clone() {
+                            for dx in spread.clone() {
+                                self.set(px + dx, py + dy, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
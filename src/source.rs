@@ -0,0 +1,694 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::compact::{CompactGrid, Samples};
+use crate::error::{Error, Result};
+use crate::georef::{Georef, PatHeader};
+use crate::grid::{read_csv_grid, Elevations, Grid};
+use crate::progress::{Monitor, Phase};
+use crate::window::Window;
+
+// Bytes read from the start of a file for magic-number detection
+const MAGIC_LEN: usize = 64;
+
+// Approximate number of cells read between progress reports
+const READ_CHUNK_CELLS: usize = 1 << 20;
+
+// GTOPO30/GMTED tile size, assumed for `.bin` files without a PAT header
+const TILE_ROWS: usize = 6000;
+const TILE_COLS: usize = 4800;
+
+// An opened elevation grid. Readers only need to provide the dimensions and
+// row access; windows and whole grids are assembled from rows by default.
+pub trait GridSource {
+    // (rows, cols)
+    fn dimensions(&self) -> (usize, usize);
+
+    fn georef(&self) -> Option<Georef> {
+        None
+    }
+
+    // Elevation value marking missing cells, if the format has one
+    fn no_data(&self) -> Option<i32> {
+        None
+    }
+
+    // Return elevations exactly as stored, without clean-up such as the
+    // binary reader's clamping of below-sea-level and no-data cells
+    fn set_raw(&mut self, _raw: bool) {}
+
+    // Append `count` rows starting at `first` to `out`, row-major
+    fn read_rows(&mut self, first: usize, count: usize, out: &mut Vec<i32>) -> Result<()>;
+
+    // Read the window of `rows` x `cols` cells whose top-left cell is (`row`, `col`)
+    fn read_window(&mut self, row: usize, col: usize, rows: usize, cols: usize) -> Result<Grid> {
+        let (total_rows, total_cols) = self.dimensions();
+        if row + rows > total_rows || col + cols > total_cols {
+            return Err(Error::InvalidParameter(format!(
+                "window {}x{} at ({}, {}) exceeds {}x{} grid",
+                rows, cols, row, col, total_rows, total_cols
+            )));
+        }
+        let mut data = Vec::with_capacity(rows * cols);
+        let mut line = Vec::with_capacity(total_cols);
+        for r in row..row + rows {
+            line.clear();
+            self.read_rows(r, 1, &mut line)?;
+            data.extend_from_slice(&line[col..col + cols]);
+        }
+        Grid::new(rows, cols, data)
+    }
+
+    fn read_all(&mut self) -> Result<Grid> {
+        self.read_all_with(&Monitor::new())
+    }
+
+    // Read the whole grid in chunks of rows, reporting `Phase::Read` progress
+    fn read_all_with(&mut self, monitor: &Monitor) -> Result<Grid> {
+        let (rows, cols) = self.dimensions();
+        let total = rows * cols;
+        let chunk = (READ_CHUNK_CELLS / cols.max(1)).max(1);
+        let mut data = Vec::with_capacity(total);
+        let mut row = 0;
+        while row < rows {
+            monitor.step(Phase::Read, row * cols, total)?;
+            let count = chunk.min(rows - row);
+            self.read_rows(row, count, &mut data)?;
+            row += count;
+        }
+        monitor.step(Phase::Read, total, total)?;
+        Grid::new(rows, cols, data)
+    }
+
+    // Read the whole grid at the narrowest sample width that holds it.
+    // Readers of 16-bit formats override this to skip the `i32` copy.
+    fn read_compact_with(&mut self, monitor: &Monitor) -> Result<CompactGrid> {
+        Ok(CompactGrid::from_grid(&self.read_all_with(monitor)?))
+    }
+
+    // Read `window` at the narrowest sample width that holds it
+    fn read_compact_window(&mut self, window: &Window) -> Result<CompactGrid> {
+        let grid = self.read_window(window.row, window.col, window.rows, window.cols)?;
+        Ok(CompactGrid::from_grid(&grid))
+    }
+}
+
+// A readable input format, registered with a `Registry`
+pub trait GridFormat {
+    fn name(&self) -> &str;
+
+    // Lower-case file extensions, without the dot
+    fn extensions(&self) -> &[&str];
+
+    // Whether the first bytes of a file identify this format
+    fn matches_magic(&self, _header: &[u8]) -> bool {
+        false
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GridSource>>;
+}
+
+// Input formats known to the reader, tried in reverse registration order so
+// later registrations can take over extensions of the built-in ones
+pub struct Registry {
+    formats: Vec<Box<dyn GridFormat>>,
+}
+
+impl Default for Registry {
+    // Registry with the built-in CSV and binary readers
+    fn default() -> Registry {
+        let mut registry = Registry::empty();
+        registry.register(Box::new(CsvFormat));
+        registry.register(Box::new(BinFormat));
+        registry.register(Box::new(AscFormat));
+        registry
+    }
+}
+
+impl Registry {
+    pub fn empty() -> Registry {
+        Registry {
+            formats: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, format: Box<dyn GridFormat>) -> &mut Registry {
+        self.formats.push(format);
+        self
+    }
+
+    pub fn formats(&self) -> impl Iterator<Item = &dyn GridFormat> {
+        self.formats.iter().rev().map(|format| format.as_ref())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&dyn GridFormat> {
+        self.formats()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    // Pick the format of a file: magic bytes first, then the extension
+    pub fn detect(&self, path: &str) -> Result<&dyn GridFormat> {
+        let mut header = Vec::with_capacity(MAGIC_LEN);
+        File::open(path)
+            .and_then(|file| file.take(MAGIC_LEN as u64).read_to_end(&mut header))
+            .map_err(|e| Error::io(path, e))?;
+        if let Some(format) = self.formats().find(|format| format.matches_magic(&header)) {
+            return Ok(format);
+        }
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        extension
+            .as_deref()
+            .and_then(|ext| {
+                self.formats()
+                    .find(|format| format.extensions().contains(&ext))
+            })
+            .ok_or_else(|| {
+                let known: Vec<String> = self
+                    .formats()
+                    .flat_map(|format| format.extensions())
+                    .map(|ext| format!(".{}", ext))
+                    .collect();
+                Error::UnsupportedFormat(format!(
+                    "'{}': unrecognised input format. Use {}",
+                    path,
+                    known.join(", ")
+                ))
+            })
+    }
+
+    pub fn open(&self, path: &str) -> Result<Box<dyn GridSource>> {
+        self.detect(path)?.open(path)
+    }
+
+    pub fn read(&self, path: &str) -> Result<Grid> {
+        self.open(path)?.read_all()
+    }
+}
+
+// Comma-separated text, one grid row per line
+pub struct CsvFormat;
+
+impl GridFormat for CsvFormat {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    // Plain numbers separated by commas and line breaks
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.contains(&b',')
+            && header.iter().all(|&b| {
+                b.is_ascii_digit() || b.is_ascii_whitespace() || matches!(b, b',' | b'-' | b'+')
+            })
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GridSource>> {
+        Ok(Box::new(CsvSource {
+            grid: read_csv_grid(path)?,
+            georef: Georef::locate(path)?,
+        }))
+    }
+}
+
+// CSV has no random access, so the whole grid is parsed when opened
+pub struct CsvSource {
+    grid: Grid,
+    georef: Option<Georef>,
+}
+
+impl GridSource for CsvSource {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.grid.rows, self.grid.cols)
+    }
+
+    fn georef(&self) -> Option<Georef> {
+        self.georef
+    }
+
+    fn read_rows(&mut self, first: usize, count: usize, out: &mut Vec<i32>) -> Result<()> {
+        read_grid_rows(&self.grid, first, count, out)
+    }
+
+    fn read_all_with(&mut self, monitor: &Monitor) -> Result<Grid> {
+        let total = self.grid.len();
+        monitor.step(Phase::Read, total, total)?;
+        Ok(self.grid.clone())
+    }
+}
+
+// Raw little-endian i16 samples, row-major from the north-west corner
+pub struct BinFormat;
+
+impl GridFormat for BinFormat {
+    fn name(&self) -> &str {
+        "bin"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bin"]
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GridSource>> {
+        Ok(Box::new(BinSource::open(path)?))
+    }
+}
+
+pub struct BinSource {
+    path: String,
+    reader: File,
+    // Raw bytes of the rows being read, reused between reads
+    buffer: Vec<u8>,
+    rows: usize,
+    cols: usize,
+    georef: Option<Georef>,
+    no_data: Option<i32>,
+    raw: bool,
+}
+
+impl BinSource {
+    // Dimensions come from the PAT header when there is one, otherwise the
+    // file must hold a full 6000x4800 tile or a square grid
+    pub fn open(path: &str) -> Result<BinSource> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let file_size = file.metadata().map_err(|e| Error::io(path, e))?.len();
+
+        let pat = Path::new(path).with_extension("pat");
+        let header = if pat.is_file() {
+            PatHeader::read(&pat)?
+        } else {
+            PatHeader::default()
+        };
+        let samples = (file_size / 2) as usize;
+        let (rows, cols) = match (header.rows, header.cols) {
+            (Some(rows), Some(cols)) => (rows, cols),
+            _ if samples == TILE_ROWS * TILE_COLS => (TILE_ROWS, TILE_COLS),
+            _ => {
+                let side = (samples as f64).sqrt().round() as usize;
+                (side, side)
+            }
+        };
+
+        // Calculate total elements and check for overflow
+        let total = rows
+            .checked_mul(cols)
+            .ok_or_else(|| Error::format(path, "grid size too large"))?;
+        let expected_size = total as u64 * 2; // Each i16 is 2 bytes
+
+        // Log file size information
+        crate::debug!(
+            "File '{}': size = {} bytes, expected size = {} bytes (for {}x{} i16 grid)",
+            path,
+            file_size,
+            expected_size,
+            rows,
+            cols
+        );
+
+        // Validate file size
+        if file_size != expected_size || total == 0 {
+            return Err(Error::format(
+                path,
+                format!(
+                    "size mismatch: expected {} bytes (for {}x{} i16 grid), found {} bytes",
+                    expected_size, rows, cols, file_size
+                ),
+            ));
+        }
+
+        crate::info!(
+            "Reading BIN grid: rows={}, cols={}, total={}",
+            rows,
+            cols,
+            total
+        );
+        Ok(BinSource {
+            path: path.to_string(),
+            reader: file,
+            buffer: Vec::new(),
+            rows,
+            cols,
+            georef: match header.georef {
+                Some(georef) => Some(georef),
+                None => Georef::locate(path)?,
+            },
+            no_data: header.no_data,
+            raw: false,
+        })
+    }
+}
+
+impl GridSource for BinSource {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn georef(&self) -> Option<Georef> {
+        self.georef
+    }
+
+    fn no_data(&self) -> Option<i32> {
+        self.no_data
+    }
+
+    fn set_raw(&mut self, raw: bool) {
+        self.raw = raw;
+    }
+
+    fn read_rows(&mut self, first: usize, count: usize, out: &mut Vec<i32>) -> Result<()> {
+        let raw = self.raw;
+        let samples = self.read_bytes(first, count)?.chunks_exact(2);
+        let values = samples.map(|b| i16::from_le_bytes([b[0], b[1]]) as i32);
+        // Clamp negative values to 0 (sea level) as per assignment requirements
+        if raw {
+            out.extend(values);
+        } else {
+            out.extend(values.map(|v| v.max(0)));
+        }
+        Ok(())
+    }
+
+    // Kept as i16, read in the same chunks as `read_all_with`
+    fn read_compact_with(&mut self, monitor: &Monitor) -> Result<CompactGrid> {
+        let (rows, cols) = (self.rows, self.cols);
+        let total = rows * cols;
+        let chunk = (READ_CHUNK_CELLS / cols).max(1);
+        let mut samples = Vec::with_capacity(total);
+        let mut row = 0;
+        while row < rows {
+            monitor.step(Phase::Read, row * cols, total)?;
+            let count = chunk.min(rows - row);
+            self.read_samples(row, count, &mut samples)?;
+            row += count;
+        }
+        if !self.raw {
+            for sample in &mut samples {
+                *sample = (*sample).max(0);
+            }
+        }
+        monitor.step(Phase::Read, total, total)?;
+        CompactGrid::new(rows, cols, Samples::I16(samples))
+    }
+
+    // Only the window's span of each row is read from disk
+    fn read_window(&mut self, row: usize, col: usize, rows: usize, cols: usize) -> Result<Grid> {
+        let window = Window {
+            row,
+            col,
+            rows,
+            cols,
+        };
+        let samples = self.read_window_samples(&window)?;
+        let raw = self.raw;
+        let data = samples
+            .iter()
+            .map(|&v| if raw { v as i32 } else { v.max(0) as i32 })
+            .collect();
+        Grid::new(rows, cols, data)
+    }
+
+    fn read_compact_window(&mut self, window: &Window) -> Result<CompactGrid> {
+        let mut samples = self.read_window_samples(window)?;
+        if !self.raw {
+            for sample in &mut samples {
+                *sample = (*sample).max(0);
+            }
+        }
+        CompactGrid::new(window.rows, window.cols, Samples::I16(samples))
+    }
+}
+
+impl BinSource {
+    // Samples of rows `first..first + count` at their native width, as
+    // stored (never clamped), for callers that keep the grid as i16
+    pub fn read_samples(&mut self, first: usize, count: usize, out: &mut Vec<i16>) -> Result<()> {
+        let samples = self.read_bytes(first, count)?.chunks_exact(2);
+        out.extend(samples.map(|b| i16::from_le_bytes([b[0], b[1]])));
+        Ok(())
+    }
+
+    // Samples of `window` as stored, one read per row of the window
+    fn read_window_samples(&mut self, window: &Window) -> Result<Vec<i16>> {
+        if window.row + window.rows > self.rows || window.col + window.cols > self.cols {
+            return Err(Error::InvalidParameter(format!(
+                "window {}x{} at ({}, {}) exceeds {}x{} grid",
+                window.rows, window.cols, window.row, window.col, self.rows, self.cols
+            )));
+        }
+        let path = &self.path;
+        let mut samples = Vec::with_capacity(window.rows * window.cols);
+        self.buffer.resize(window.cols * 2, 0);
+        for row in window.row..window.row + window.rows {
+            let offset = (row * self.cols + window.col) * 2;
+            self.reader
+                .seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| self.reader.read_exact(&mut self.buffer))
+                .map_err(|e| Error::io(path, e))?;
+            let bytes = self.buffer.chunks_exact(2);
+            samples.extend(bytes.map(|b| i16::from_le_bytes([b[0], b[1]])));
+        }
+        Ok(samples)
+    }
+
+    // Bytes of rows `first..first + count`, read with a single call
+    fn read_bytes(&mut self, first: usize, count: usize) -> Result<&[u8]> {
+        if first + count > self.rows {
+            return Err(Error::InvalidParameter(format!(
+                "rows {}..{} outside {}-row grid",
+                first,
+                first + count,
+                self.rows
+            )));
+        }
+        let path = &self.path;
+        self.buffer.resize(count * self.cols * 2, 0);
+        self.reader
+            .seek(SeekFrom::Start((first * self.cols * 2) as u64))
+            .and_then(|_| self.reader.read_exact(&mut self.buffer))
+            .map_err(|e| Error::io(path, e))?;
+        Ok(&self.buffer)
+    }
+}
+
+// ESRI ASCII grid: a `key value` header followed by whitespace-separated rows
+pub struct AscFormat;
+
+impl GridFormat for AscFormat {
+    fn name(&self) -> &str {
+        "asc"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["asc"]
+    }
+
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.len() >= 5 && header[..5].eq_ignore_ascii_case(b"ncols")
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GridSource>> {
+        Ok(Box::new(AscSource::open(path)?))
+    }
+}
+
+// Parsed in full when opened, like CSV. Whole-metre grids are kept as i32
+// samples; any fractional value makes them f32, rounded in `Grid`s but exact
+// in compact reads, so that the sweep orders cells by their exact heights.
+pub struct AscSource {
+    grid: CompactGrid,
+    georef: Georef,
+    no_data: Option<i32>,
+}
+
+impl AscSource {
+    pub fn open(path: &str) -> Result<AscSource> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let mut lines = content.lines().enumerate().peekable();
+        let mut header = HashMap::new();
+
+        // Header lines start with a keyword, data lines with a number
+        while let Some(&(line_number, line)) = lines.peek() {
+            let mut parts = line.split_whitespace();
+            let key = match parts.next() {
+                Some(key) if key.starts_with(|c: char| c.is_ascii_alphabetic()) => key,
+                _ => break,
+            };
+            let value: f64 = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| Error::Parse {
+                    path: path.to_string(),
+                    line: line_number + 1,
+                    column: 2,
+                    message: format!("invalid '{}' entry", key),
+                })?;
+            header.insert(key.to_ascii_lowercase(), value);
+            lines.next();
+        }
+
+        let get = |key: &str| {
+            header
+                .get(key)
+                .copied()
+                .ok_or_else(|| Error::format(path, format!("missing '{}' in header", key)))
+        };
+        let rows = get("nrows")? as usize;
+        let cols = get("ncols")? as usize;
+        let (cell_width, cell_height) = match get("cellsize") {
+            Ok(size) => (size, size),
+            Err(_) => (get("dx")?, get("dy")?),
+        };
+        let west = match get("xllcorner") {
+            Ok(x) => x,
+            Err(_) => get("xllcenter")? - cell_width / 2.0,
+        };
+        let south = match get("yllcorner") {
+            Ok(y) => y,
+            Err(_) => get("yllcenter")? - cell_height / 2.0,
+        };
+
+        let total = rows.checked_mul(cols).ok_or_else(|| {
+            Error::format(path, format!("header promises {}x{} cells", rows, cols))
+        })?;
+        // Every value takes two bytes or more, which bounds what a header
+        // promising more than the file holds can reserve
+        let capacity = total.min(content.len() / 2 + 1);
+        let mut samples = Samples::I32(Vec::with_capacity(capacity));
+        for (line_number, line) in lines {
+            for (column, value) in line.split_whitespace().enumerate() {
+                let elevation: f64 = value.parse().map_err(|_| Error::Parse {
+                    path: path.to_string(),
+                    line: line_number + 1,
+                    column: column + 1,
+                    message: format!("invalid number '{}'", value),
+                })?;
+                match &mut samples {
+                    Samples::I32(values) if elevation.fract() == 0.0 => {
+                        values.push(elevation as i32)
+                    }
+                    Samples::I32(values) => {
+                        // Converted in place from the first fractional value on
+                        let mut exact: Vec<f32> = std::mem::take(values)
+                            .into_iter()
+                            .map(|v| v as f32)
+                            .collect();
+                        exact.push(elevation as f32);
+                        samples = Samples::F32(exact);
+                    }
+                    Samples::F32(values) => values.push(elevation as f32),
+                    Samples::I16(_) | Samples::U16(_) => unreachable!("ASC samples are 32-bit"),
+                }
+            }
+        }
+        if samples.len() != total {
+            return Err(Error::format(
+                path,
+                format!(
+                    "header promises {}x{} cells, found {}",
+                    rows,
+                    cols,
+                    samples.len()
+                ),
+            ));
+        }
+
+        crate::info!("Read ASCII grid: rows={}, cols={}", rows, cols);
+        Ok(AscSource {
+            grid: CompactGrid::new(rows, cols, samples)?,
+            georef: Georef {
+                west,
+                north: south + rows as f64 * cell_height,
+                cell_width,
+                cell_height,
+            },
+            no_data: header.get("nodata_value").map(|&v| v.round() as i32),
+        })
+    }
+}
+
+impl GridSource for AscSource {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.grid.rows, self.grid.cols)
+    }
+
+    fn georef(&self) -> Option<Georef> {
+        Some(self.georef)
+    }
+
+    fn no_data(&self) -> Option<i32> {
+        self.no_data
+    }
+
+    fn read_rows(&mut self, first: usize, count: usize, out: &mut Vec<i32>) -> Result<()> {
+        let cols = self.grid.cols;
+        if first + count > self.grid.rows {
+            return Err(Error::InvalidParameter(format!(
+                "rows {}..{} outside {}-row grid",
+                first,
+                first + count,
+                self.grid.rows
+            )));
+        }
+        out.extend((first * cols..(first + count) * cols).map(|i| self.grid.metres(i)));
+        Ok(())
+    }
+
+    fn read_all_with(&mut self, monitor: &Monitor) -> Result<Grid> {
+        let total = self.grid.len();
+        monitor.step(Phase::Read, total, total)?;
+        Ok(self.grid.to_grid())
+    }
+
+    fn read_compact_with(&mut self, monitor: &Monitor) -> Result<CompactGrid> {
+        if let Samples::I32(_) = self.grid.samples {
+            return Ok(CompactGrid::from_grid(&self.read_all_with(monitor)?));
+        }
+        let total = self.grid.len();
+        monitor.step(Phase::Read, total, total)?;
+        Ok(self.grid.clone())
+    }
+
+    fn read_compact_window(&mut self, window: &Window) -> Result<CompactGrid> {
+        let Samples::F32(exact) = &self.grid.samples else {
+            let grid = self.read_window(window.row, window.col, window.rows, window.cols)?;
+            return Ok(CompactGrid::from_grid(&grid));
+        };
+        let (rows, cols) = (self.grid.rows, self.grid.cols);
+        if window.row + window.rows > rows || window.col + window.cols > cols {
+            return Err(Error::InvalidParameter(format!(
+                "window {}x{} at ({}, {}) exceeds {}x{} grid",
+                window.rows, window.cols, window.row, window.col, rows, cols
+            )));
+        }
+        let samples = (window.row..window.row + window.rows)
+            .flat_map(|row| &exact[row * cols + window.col..row * cols + window.col + window.cols])
+            .copied()
+            .collect();
+        CompactGrid::new(window.rows, window.cols, Samples::F32(samples))
+    }
+}
+
+// Rows of an in-memory grid, for readers that parse the whole file up front
+fn read_grid_rows(grid: &Grid, first: usize, count: usize, out: &mut Vec<i32>) -> Result<()> {
+    let rows = grid
+        .data
+        .get(first * grid.cols..(first + count) * grid.cols)
+        .ok_or_else(|| {
+            Error::InvalidParameter(format!(
+                "rows {}..{} outside {}-row grid",
+                first,
+                first + count,
+                grid.rows
+            ))
+        })?;
+    out.extend_from_slice(rows);
+    Ok(())
+}
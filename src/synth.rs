@@ -0,0 +1,334 @@
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+
+// Synthetic terrain for tests and benchmarks. Every kind is reproducible from
+// its seed, so large inputs can be regenerated instead of shipped.
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Terrain {
+    // Midpoint displacement on a square lattice
+    DiamondSquare,
+    // Octaves of Perlin gradient noise
+    Noise,
+    // Diagonal terraces rising across the grid: flat steps, no strict peaks
+    Staircase,
+    // Flat-topped mesa in the middle of low, rough ground
+    Plateau,
+    // Every other cell high with jittered heights: a dense field of peaks
+    // joined to each other only diagonally
+    Checkerboard,
+    // Flat ground with a single one-cell spike
+    Spike,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 6] = [
+        Terrain::DiamondSquare,
+        Terrain::Noise,
+        Terrain::Staircase,
+        Terrain::Plateau,
+        Terrain::Checkerboard,
+        Terrain::Spike,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Terrain> {
+        match name.to_ascii_lowercase().as_str() {
+            "diamond-square" | "diamond" => Some(Terrain::DiamondSquare),
+            "noise" | "perlin" => Some(Terrain::Noise),
+            "staircase" => Some(Terrain::Staircase),
+            "plateau" => Some(Terrain::Plateau),
+            "checkerboard" => Some(Terrain::Checkerboard),
+            "spike" => Some(Terrain::Spike),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Terrain::DiamondSquare => "diamond-square",
+            Terrain::Noise => "noise",
+            Terrain::Staircase => "staircase",
+            Terrain::Plateau => "plateau",
+            Terrain::Checkerboard => "checkerboard",
+            Terrain::Spike => "spike",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainOptions {
+    pub terrain: Terrain,
+    pub rows: usize,
+    pub cols: usize,
+    pub seed: u64,
+    // Highest elevation; generated values span 0..=relief
+    pub relief: i32,
+    // Amplitude kept from one octave or subdivision to the next, in (0, 1)
+    pub roughness: f64,
+}
+
+impl Default for TerrainOptions {
+    fn default() -> Self {
+        TerrainOptions {
+            terrain: Terrain::DiamondSquare,
+            rows: 512,
+            cols: 512,
+            seed: 1,
+            relief: 3000,
+            roughness: 0.55,
+        }
+    }
+}
+
+// Generate a grid as described by `options`
+pub fn generate(options: &TerrainOptions) -> Result<Grid> {
+    let (rows, cols) = (options.rows, options.cols);
+    if rows == 0 || cols == 0 {
+        return Err(Error::InvalidParameter(format!(
+            "terrain size {}x{}",
+            rows, cols
+        )));
+    }
+    if options.relief < 1 {
+        return Err(Error::InvalidParameter(format!(
+            "terrain relief {}",
+            options.relief
+        )));
+    }
+    if !(options.roughness > 0.0 && options.roughness < 1.0) {
+        return Err(Error::InvalidParameter(format!(
+            "terrain roughness {}",
+            options.roughness
+        )));
+    }
+    let mut rng = Rng::new(options.seed);
+    let relief = options.relief;
+
+    let data = match options.terrain {
+        Terrain::DiamondSquare => scale(
+            &diamond_square(rows, cols, options.roughness, &mut rng),
+            relief,
+        ),
+        Terrain::Noise => scale(
+            &fractal_noise(rows, cols, options.roughness, &mut rng),
+            relief,
+        ),
+        Terrain::Staircase => {
+            let span = rows + cols - 1;
+            let steps = (relief as usize).min(span.div_ceil(4)).max(1);
+            (0..rows * cols)
+                .map(|i| {
+                    let step = (i / cols + i % cols) * steps / span;
+                    (step * relief as usize / steps) as i32
+                })
+                .collect()
+        }
+        Terrain::Plateau => {
+            let ground = (relief / 10).max(1);
+            (0..rows * cols)
+                .map(|i| {
+                    let (x, y) = (i / cols, i % cols);
+                    let inside = x >= rows / 4
+                        && x < rows - rows / 4
+                        && y >= cols / 4
+                        && y < cols - cols / 4;
+                    if inside {
+                        relief
+                    } else {
+                        rng.range(0, ground)
+                    }
+                })
+                .collect()
+        }
+        Terrain::Checkerboard => (0..rows * cols)
+            .map(|i| {
+                if (i / cols + i % cols) % 2 == 0 {
+                    rng.range(relief / 2, relief)
+                } else {
+                    0
+                }
+            })
+            .collect(),
+        Terrain::Spike => {
+            let mut data = vec![0; rows * cols];
+            data[rng.next_u64() as usize % (rows * cols)] = relief;
+            data
+        }
+    };
+    Grid::new(rows, cols, data)
+}
+
+// Heights stretched linearly onto 0..=relief
+fn scale(heights: &[f32], relief: i32) -> Vec<i32> {
+    let (low, high) = heights
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(low, high), &h| {
+            (low.min(h), high.max(h))
+        });
+    let range = (high - low).max(f32::EPSILON) as f64;
+    heights
+        .iter()
+        .map(|&h| ((h - low) as f64 / range * relief as f64).round() as i32)
+        .collect()
+}
+
+// Diamond-square over a lattice of `step`-spaced seed points just covering
+// the grid. The step is the largest power of two below the shorter side,
+// so the lattice overhangs the grid by less than one step.
+fn diamond_square(rows: usize, cols: usize, roughness: f64, rng: &mut Rng) -> Vec<f32> {
+    let mut step = 1;
+    while step * 2 < rows.min(cols) {
+        step *= 2;
+    }
+    let height = (rows - 1).div_ceil(step) * step + 1;
+    let width = (cols - 1).div_ceil(step) * step + 1;
+    let mut h = vec![0f32; height * width];
+    for x in (0..height).step_by(step) {
+        for y in (0..width).step_by(step) {
+            h[x * width + y] = rng.unit() as f32;
+        }
+    }
+
+    let mut amplitude = roughness as f32;
+    while step > 1 {
+        let half = step / 2;
+        // Square step: centre of each square from its corners
+        for x in (half..height).step_by(step) {
+            for y in (half..width).step_by(step) {
+                let sum = h[(x - half) * width + y - half]
+                    + h[(x - half) * width + y + half]
+                    + h[(x + half) * width + y - half]
+                    + h[(x + half) * width + y + half];
+                h[x * width + y] = sum / 4.0 + amplitude * rng.signed() as f32;
+            }
+        }
+        // Diamond step: edge midpoints from the neighbours that exist
+        for x in (0..height).step_by(half) {
+            let first = if (x / half) % 2 == 0 { half } else { 0 };
+            for y in (first..width).step_by(step) {
+                let mut sum = 0.0;
+                let mut count = 0.0;
+                if x >= half {
+                    sum += h[(x - half) * width + y];
+                    count += 1.0;
+                }
+                if x + half < height {
+                    sum += h[(x + half) * width + y];
+                    count += 1.0;
+                }
+                if y >= half {
+                    sum += h[x * width + y - half];
+                    count += 1.0;
+                }
+                if y + half < width {
+                    sum += h[x * width + y + half];
+                    count += 1.0;
+                }
+                h[x * width + y] = sum / count + amplitude * rng.signed() as f32;
+            }
+        }
+        amplitude *= roughness as f32;
+        step = half;
+    }
+
+    (0..rows)
+        .flat_map(|x| h[x * width..x * width + cols].iter().copied())
+        .collect()
+}
+
+// Unit gradients Perlin noise picks from
+const GRADIENTS: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (0.707_106_77, 0.707_106_77),
+    (-0.707_106_77, 0.707_106_77),
+    (0.707_106_77, -0.707_106_77),
+    (-0.707_106_77, -0.707_106_77),
+];
+
+// Fractal Brownian motion: octaves of Perlin noise, each at twice the
+// frequency and `roughness` times the amplitude of the previous one. The
+// first octave has about four features across the longer side.
+fn fractal_noise(rows: usize, cols: usize, roughness: f64, rng: &mut Rng) -> Vec<f32> {
+    let base = 4.0 / rows.max(cols) as f32;
+    let mut octaves = Vec::new();
+    let mut frequency = base;
+    while frequency < 1.0 && octaves.len() < 12 {
+        octaves.push((rng.next_u64(), frequency));
+        frequency *= 2.0;
+    }
+    if octaves.is_empty() {
+        octaves.push((rng.next_u64(), 0.5));
+    }
+
+    let mut heights = Vec::with_capacity(rows * cols);
+    for x in 0..rows {
+        for y in 0..cols {
+            let mut amplitude = 1.0;
+            let mut sum = 0.0;
+            for &(seed, frequency) in &octaves {
+                sum += amplitude * perlin(seed, x as f32 * frequency, y as f32 * frequency);
+                amplitude *= roughness as f32;
+            }
+            heights.push(sum);
+        }
+    }
+    heights
+}
+
+fn perlin(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let dot = |dx: i64, dy: i64| {
+        let hash = mix(seed
+            ^ ((ix + dx) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ ((iy + dy) as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f));
+        let (gx, gy) = GRADIENTS[(hash % 8) as usize];
+        gx * (fx - dx as f32) + gy * (fy - dy as f32)
+    };
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v) = (fade(fx), fade(fy));
+    let top = dot(0, 0) + u * (dot(1, 0) - dot(0, 0));
+    let bottom = dot(0, 1) + u * (dot(1, 1) - dot(0, 1));
+    top + v * (bottom - top)
+}
+
+// SplitMix64 finaliser
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Small seeded pseudo-random generator (SplitMix64); not for cryptography
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+
+    // Uniform in low..=high
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        let span = (high as i64 - low as i64 + 1) as u64;
+        (low as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    // Uniform in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [-1, 1)
+    pub fn signed(&mut self) -> f64 {
+        self.unit() * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use crate::output::json_string;
+use crate::progress::Phase;
+
+// Wall time and memory high-water mark of one phase
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhaseTiming {
+    pub name: &'static str,
+    pub seconds: f64,
+    // Peak resident set size during the phase, where the platform reports it
+    pub peak_rss_kb: Option<u64>,
+}
+
+// Per-phase timings of a run. Phases are started explicitly with `begin` or
+// implicitly by feeding progress reports to `observe`.
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: RefCell<Vec<PhaseTiming>>,
+    current: RefCell<Option<(&'static str, Instant)>>,
+    reset_rss: bool,
+}
+
+impl Timings {
+    // Timings whose memory peaks cover the run so far at the end of each phase
+    pub fn new() -> Timings {
+        Timings::default()
+    }
+
+    // Timings that restart the process-wide memory high-water mark at each
+    // phase, so each peak covers its own phase. This clears the mark for the
+    // whole process, and is meant for the timed program itself.
+    pub fn with_rss_reset() -> Timings {
+        Timings {
+            reset_rss: true,
+            ..Timings::default()
+        }
+    }
+
+    // Start timing `name`, ending the phase in progress
+    pub fn begin(&self, name: &'static str) {
+        self.end();
+        if self.reset_rss {
+            reset_peak_rss();
+        }
+        *self.current.borrow_mut() = Some((name, Instant::now()));
+    }
+
+    pub fn end(&self) {
+        if let Some((name, start)) = self.current.borrow_mut().take() {
+            self.phases.borrow_mut().push(PhaseTiming {
+                name,
+                seconds: start.elapsed().as_secs_f64(),
+                peak_rss_kb: peak_rss_kb(),
+            });
+        }
+    }
+
+    // Progress callback: a phase begins with its first report, unless already
+    // begun, and ends once all its cells are processed
+    pub fn observe(&self, phase: Phase, processed: usize, total: usize) {
+        let running = self.current.borrow().map(|(name, _)| name);
+        if running != Some(phase.name()) {
+            self.begin(phase.name());
+        }
+        if processed == total {
+            self.end();
+        }
+    }
+
+    pub fn phases(&self) -> Vec<PhaseTiming> {
+        self.end();
+        self.phases.borrow().clone()
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let phases = self.phases();
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"phases\": [")?;
+        for (i, phase) in phases.iter().enumerate() {
+            writeln!(
+                out,
+                "    {{\"phase\": {}, \"seconds\": {:.6}, \"peak_rss_kb\": {}}}{}",
+                json_string(phase.name),
+                phase.seconds,
+                phase
+                    .peak_rss_kb
+                    .map_or("null".to_string(), |kb| kb.to_string()),
+                if i + 1 < phases.len() { "," } else { "" }
+            )?;
+        }
+        writeln!(out, "  ],")?;
+        writeln!(
+            out,
+            "  \"total_seconds\": {:.6}",
+            phases.iter().map(|phase| phase.seconds).sum::<f64>()
+        )?;
+        writeln!(out, "}}")
+    }
+}
+
+// Peak resident set size of this process so far (VmHWM), Linux only
+pub fn peak_rss_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+// Restart the VmHWM high-water mark so the next reading covers one phase only.
+// Not every kernel allows this, in which case the mark covers the run so far.
+fn reset_peak_rss() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
@@ -0,0 +1,129 @@
+use crate::error::{Error, Result};
+use crate::georef::Georef;
+use crate::prominence::Peak;
+
+// Rectangle of cells cut out of a larger grid: `rows` x `cols` cells whose
+// top-left cell is (`row`, `col`) in the grid's frame
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Window {
+    pub row: usize,
+    pub col: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Window {
+    // Window of rows `first_row..=last_row` and columns `first_col..=last_col`,
+    // checked against a `grid_rows` x `grid_cols` grid
+    pub fn from_ranges(
+        (first_row, last_row): (usize, usize),
+        (first_col, last_col): (usize, usize),
+        (grid_rows, grid_cols): (usize, usize),
+    ) -> Result<Window> {
+        if first_row > last_row
+            || first_col > last_col
+            || last_row >= grid_rows
+            || last_col >= grid_cols
+        {
+            return Err(Error::InvalidParameter(format!(
+                "window rows {}..={}, cols {}..={} outside {}x{} grid",
+                first_row, last_row, first_col, last_col, grid_rows, grid_cols
+            )));
+        }
+        Ok(Window {
+            row: first_row,
+            col: first_col,
+            rows: last_row - first_row + 1,
+            cols: last_col - first_col + 1,
+        })
+    }
+
+    // Cells of a `grid_rows` x `grid_cols` grid whose centres lie within the
+    // longitude range `west..=east` and latitude range `south..=north`
+    pub fn from_bbox(
+        georef: &Georef,
+        (west, south, east, north): (f64, f64, f64, f64),
+        (grid_rows, grid_cols): (usize, usize),
+    ) -> Result<Window> {
+        let empty = || {
+            Error::InvalidParameter(format!(
+                "bounding box {},{},{},{} holds no cells of the grid",
+                west, south, east, north
+            ))
+        };
+        if west > east || south > north {
+            return Err(empty());
+        }
+        // Cell i covers [edge + i * size, edge + (i + 1) * size); its centre
+        // is within [low, high] for i in ceil(low' - 0.5)..=floor(high' - 0.5)
+        let first = |offset: f64| (offset - 0.5).ceil().max(0.0);
+        let last = |offset: f64, count: usize| (offset - 0.5).floor().min(count as f64 - 1.0);
+        let first_col = first((west - georef.west) / georef.cell_width);
+        let last_col = last((east - georef.west) / georef.cell_width, grid_cols);
+        let first_row = first((georef.north - north) / georef.cell_height);
+        let last_row = last((georef.north - south) / georef.cell_height, grid_rows);
+        if first_col > last_col || first_row > last_row {
+            return Err(empty());
+        }
+        Ok(Window {
+            row: first_row as usize,
+            col: first_col as usize,
+            rows: (last_row - first_row) as usize + 1,
+            cols: (last_col - first_col) as usize + 1,
+        })
+    }
+
+    // Sides of the window cut through a `grid_rows` x `grid_cols` grid,
+    // beyond which there is terrain the window does not see
+    pub fn open_edges(&self, grid_rows: usize, grid_cols: usize) -> Edges {
+        Edges {
+            top: self.row > 0,
+            bottom: self.row + self.rows < grid_rows,
+            left: self.col > 0,
+            right: self.col + self.cols < grid_cols,
+        }
+    }
+
+    // Georeference of the window's own cells
+    pub fn georef(&self, georef: &Georef) -> Georef {
+        Georef {
+            west: georef.west + self.col as f64 * georef.cell_width,
+            north: georef.north - self.row as f64 * georef.cell_height,
+            ..*georef
+        }
+    }
+
+    // Move peaks found in the window to the frame of the full grid
+    pub fn to_grid_frame(&self, peaks: &mut [Peak]) {
+        for peak in peaks {
+            peak.peak_x += self.row;
+            peak.peak_y += self.col;
+            peak.col_x = peak.col_x.map(|x| x + self.row);
+            peak.col_y = peak.col_y.map(|y| y + self.col);
+        }
+    }
+}
+
+// Grid sides across which terrain continues unseen. A peak whose island
+// reaches such a side may have a higher key col outside the grid.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Edges {
+    pub top: bool,
+    pub bottom: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl Edges {
+    pub fn any(&self) -> bool {
+        self.top || self.bottom || self.left || self.right
+    }
+
+    // Whether cell (`row`, `col`) of a `rows` x `cols` grid lies on an open side
+    pub fn contains(&self, row: usize, col: usize, rows: usize, cols: usize) -> bool {
+        (self.top && row == 0)
+            || (self.bottom && row + 1 == rows)
+            || (self.left && col == 0)
+            || (self.right && col + 1 == cols)
+    }
+}
@@ -0,0 +1,320 @@
+// Batch runs over directories of small synthetic tiles: results match
+// single-tile runs, failures are contained, and interrupted runs resume.
+
+use std::fs;
+use std::process::{Command, Stdio};
+use std::thread;
+
+mod common;
+
+use common::{path, scratch};
+use topographic_prominence::batch::{list_tiles, run_batch, BatchOptions, JOURNAL, SUMMARY};
+use topographic_prominence::output::{write_peaks, OutputFormat, RunInfo};
+use topographic_prominence::synth::{self, Terrain, TerrainOptions};
+use topographic_prominence::{
+    compute_prominence, read_grid, write_grid, CancelToken, Error, ProminenceOptions,
+};
+
+// Five tiles of different kinds and formats, and one that cannot be read
+fn write_tiles(dir: &std::path::Path) -> Vec<String> {
+    fs::create_dir_all(dir).unwrap();
+    let mut tiles = Vec::new();
+    for (i, terrain) in Terrain::ALL.iter().take(5).enumerate() {
+        let grid = synth::generate(&TerrainOptions {
+            terrain: *terrain,
+            rows: 20 + i * 7,
+            cols: 30 + i * 3,
+            seed: i as u64,
+            ..TerrainOptions::default()
+        })
+        .unwrap();
+        let name = format!("T{}.{}", i, if i % 2 == 0 { "csv" } else { "asc" });
+        write_grid(&path(dir, &name), &grid, None, None).unwrap();
+        tiles.push(path(dir, &name));
+    }
+    fs::write(dir.join("T9.csv"), "1,2,3\n4,oops,6\n").unwrap();
+    tiles.push(path(dir, "T9.csv"));
+    tiles.sort();
+    tiles
+}
+
+fn options() -> BatchOptions {
+    BatchOptions {
+        jobs: 3,
+        format: OutputFormat::Csv,
+        prominence: ProminenceOptions::new().max_peaks(20),
+        resume: true,
+    }
+}
+
+// What `compute --format csv --max-peaks 20` writes for one tile
+fn single_run(tile: &str) -> String {
+    let grid = read_grid(tile).unwrap();
+    let options = ProminenceOptions::new().max_peaks(20);
+    let peaks = compute_prominence(&grid, &options).peaks;
+    let info = RunInfo {
+        input: tile,
+        rows: grid.rows,
+        cols: grid.cols,
+        max_peaks: Some(20),
+        min_prominence: 0,
+        georef: None,
+        window: None,
+    };
+    let mut out = Vec::new();
+    write_peaks(&mut out, OutputFormat::Csv, &peaks, &info).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn batch_matches_single_runs() {
+    let dir = scratch("batch-single");
+    let tiles = write_tiles(&dir.join("tiles"));
+    let out = path(&dir, "out");
+
+    let mut seen = Vec::new();
+    let summary = run_batch(&tiles, &out, &options(), &CancelToken::new(), |tile| {
+        seen.push(tile.name.clone())
+    })
+    .unwrap();
+    seen.sort();
+    assert_eq!(seen, ["T0", "T1", "T2", "T3", "T4", "T9"]);
+
+    let names: Vec<&str> = summary.tiles.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["T0", "T1", "T2", "T3", "T4", "T9"]);
+    let failures: Vec<&str> = summary.failures().map(|t| t.name.as_str()).collect();
+    assert_eq!(failures, ["T9"]);
+    for (tile, report) in tiles.iter().zip(&summary.tiles).take(5) {
+        assert!(report.succeeded() && !report.resumed);
+        let written = fs::read_to_string(dir.join("out").join(format!("{}.csv", report.name)));
+        assert_eq!(written.unwrap(), single_run(tile), "{}", tile);
+        assert!(report.total_seconds >= report.read_seconds + report.compute_seconds);
+    }
+    assert!(!dir.join("out/T9.csv").exists());
+
+    let csv = fs::read_to_string(dir.join("out").join(SUMMARY)).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert!(lines[1].starts_with("T0,") && lines[1].contains(",ok,"));
+    assert!(lines[6].starts_with("T9,") && lines[6].contains(",failed,"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn interrupted_runs_resume() {
+    let dir = scratch("batch-resume");
+    let tiles = write_tiles(&dir.join("tiles"));
+    let out = path(&dir, "out");
+    run_batch(&tiles, &out, &options(), &CancelToken::new(), |_| {}).unwrap();
+
+    // An interruption: one result lost, the journal cut mid-line
+    fs::remove_file(dir.join("out/T2.csv")).unwrap();
+    let journal = dir.join("out").join(JOURNAL);
+    let mut text = fs::read_to_string(&journal).unwrap();
+    text.push_str("{\"name\": \"T3\", \"inp");
+    fs::write(&journal, text).unwrap();
+    // The failing tile is fixed in the meantime
+    fs::write(dir.join("tiles/T9.csv"), "1,2,3\n4,9,6\n").unwrap();
+
+    let summary = run_batch(&tiles, &out, &options(), &CancelToken::new(), |_| {}).unwrap();
+    let resumed: Vec<(&str, bool)> = summary
+        .tiles
+        .iter()
+        .map(|t| (t.name.as_str(), t.resumed))
+        .collect();
+    assert_eq!(
+        resumed,
+        [
+            ("T0", true),
+            ("T1", true),
+            ("T2", false),
+            ("T3", true),
+            ("T4", true),
+            ("T9", false)
+        ]
+    );
+    assert_eq!(summary.failures().count(), 0);
+    assert_eq!(
+        fs::read_to_string(dir.join("out/T2.csv")).unwrap(),
+        single_run(&tiles[2])
+    );
+    // Resumed tiles keep their first run's figures
+    assert!(summary.tiles[0].rows == 20 && summary.tiles[0].peaks > 0);
+
+    // Other options make earlier results stale
+    let fewer = BatchOptions {
+        prominence: ProminenceOptions::new().max_peaks(5),
+        ..options()
+    };
+    let summary = run_batch(&tiles, &out, &fewer, &CancelToken::new(), |_| {}).unwrap();
+    assert!(summary.tiles.iter().all(|t| !t.resumed));
+    let lines = fs::read_to_string(dir.join("out/T0.csv"))
+        .unwrap()
+        .lines()
+        .count();
+    assert_eq!(lines, 6);
+    let summary = run_batch(&tiles, &out, &fewer, &CancelToken::new(), |_| {}).unwrap();
+    assert!(summary.tiles.iter().all(|t| t.resumed));
+
+    // Starting over recomputes everything
+    let restart = BatchOptions {
+        resume: false,
+        ..options()
+    };
+    let summary = run_batch(&tiles, &out, &restart, &CancelToken::new(), |_| {}).unwrap();
+    assert!(summary.tiles.iter().all(|t| !t.resumed));
+    let lines = fs::read_to_string(&journal).unwrap().lines().count();
+    assert_eq!(lines, 6);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cancelled_run_keeps_no_partial_results() {
+    let dir = scratch("batch-cancel");
+    let tiles = write_tiles(&dir.join("tiles"));
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let result = run_batch(&tiles, &path(&dir, "out"), &options(), &cancel, |_| {});
+    assert!(matches!(result, Err(Error::Cancelled)));
+    let written: Vec<_> = fs::read_dir(dir.join("out"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(written, [JOURNAL]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tile_lists() {
+    let dir = scratch("batch-lists");
+    let tiles = write_tiles(&dir.join("tiles"));
+    fs::write(dir.join("tiles/notes.txt"), "not a tile").unwrap();
+    fs::create_dir(dir.join("tiles/nested.csv")).unwrap();
+    assert_eq!(list_tiles(&path(&dir, "tiles")).unwrap(), tiles);
+
+    fs::write(
+        dir.join("manifest.txt"),
+        "# release tiles\ntiles/T1.asc\n\n  tiles/T0.csv  \n",
+    )
+    .unwrap();
+    assert_eq!(
+        list_tiles(&path(&dir, "manifest.txt")).unwrap(),
+        [path(&dir, "tiles/T1.asc"), path(&dir, "tiles/T0.csv")]
+    );
+
+    fs::write(dir.join("empty.txt"), "# nothing yet\n").unwrap();
+    assert!(list_tiles(&path(&dir, "empty.txt")).is_err());
+    assert!(list_tiles(&path(&dir, "missing.txt")).is_err());
+
+    // Two tiles may not share a result file
+    let twins = [path(&dir, "tiles/T0.csv"), path(&dir, "other/T0.asc")];
+    let result = run_batch(
+        &twins,
+        &path(&dir, "out"),
+        &options(),
+        &CancelToken::new(),
+        |_| {},
+    );
+    assert!(matches!(result, Err(Error::InvalidParameter(_))));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn batch_command() {
+    let dir = scratch("batch-command");
+    write_tiles(&dir.join("tiles"));
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+            .arg("batch")
+            .args(args)
+            .arg("-q")
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["tiles", "--out", "out", "--jobs", "2", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[6/6]"), "{}", stdout);
+    assert!(stdout.contains("6 tiles, 1 failed"), "{}", stdout);
+    assert!(dir.join("out/T3.json").is_file());
+
+    fs::remove_file(dir.join("tiles/T9.csv")).unwrap();
+    let output = run(&["tiles", "--out", "out", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("T0 done by an earlier run"), "{}", stdout);
+
+    assert_eq!(run(&["tiles", "--out", "tiles"]).status.code(), Some(2));
+    assert_eq!(
+        run(&["tiles", "--out", "out", "--jobs", "0"]).status.code(),
+        Some(2)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Ctrl-C stops the command with the finished tiles kept for the next run
+#[cfg(unix)]
+#[test]
+fn interrupted_command_resumes() {
+    use std::time::{Duration, Instant};
+
+    let dir = scratch("batch-interrupt");
+    fs::create_dir_all(dir.join("tiles")).unwrap();
+    for i in 0..24 {
+        let grid = synth::generate(&TerrainOptions {
+            rows: 400,
+            cols: 400,
+            seed: i,
+            ..TerrainOptions::default()
+        })
+        .unwrap();
+        write_grid(
+            &path(&dir, &format!("tiles/T{:02}.asc", i)),
+            &grid,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    let command = || {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_topographic_prominence"));
+        command
+            .args(["batch", "tiles", "--out", "out", "--jobs", "2", "-q"])
+            .current_dir(&dir);
+        command
+    };
+
+    let mut child = command()
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let journal = dir.join("out").join(JOURNAL);
+    let started = Instant::now();
+    while fs::read_to_string(&journal).map_or(true, |lines| lines.is_empty()) {
+        assert!(started.elapsed() < Duration::from_secs(60));
+        thread::sleep(Duration::from_millis(5));
+    }
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+
+    let journaled = fs::read_to_string(&journal).unwrap().lines().count();
+    assert!(journaled < 24, "{} tiles journaled", journaled);
+    for entry in fs::read_dir(dir.join("out")).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        assert!(!name.ends_with(".partial"), "{}", name);
+    }
+
+    let output = command().output().unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("done by an earlier run"), "{}", stdout);
+    assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 24);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,221 @@
+// Highest saddles between pairs of cells checked against a search over
+// elevation thresholds, and ridge routes checked step by step.
+
+use std::collections::VecDeque;
+use std::process::Command;
+
+mod common;
+
+use common::random_grid;
+use topographic_prominence::bottleneck::{bottleneck, ridge_path};
+use topographic_prominence::synth::Rng;
+use topographic_prominence::{
+    compute_prominence, Georef, Grid, Mask, MergeTree, ProminenceOptions,
+};
+
+fn random_cell(rng: &mut Rng, grid: &Grid) -> (usize, usize) {
+    (
+        rng.range(0, grid.rows as i32 - 1) as usize,
+        rng.range(0, grid.cols as i32 - 1) as usize,
+    )
+}
+
+fn merges(grid: &Grid, options: ProminenceOptions) -> MergeTree {
+    compute_prominence(grid, &options.track_merges(true))
+        .merges
+        .unwrap()
+}
+
+// Whether `to` can be reached from `from` through cells at or above
+// `floor` that `inside` accepts
+fn connected(
+    grid: &Grid,
+    from: (usize, usize),
+    to: (usize, usize),
+    floor: i32,
+    inside: &dyn Fn(usize) -> bool,
+) -> bool {
+    let cols = grid.cols;
+    let usable = |cell: usize| inside(cell) && grid.data[cell] >= floor;
+    let (start, end) = (from.0 * cols + from.1, to.0 * cols + to.1);
+    if !usable(start) || !usable(end) {
+        return false;
+    }
+    let mut seen = vec![false; grid.len()];
+    seen[start] = true;
+    let mut queue = VecDeque::from([start]);
+    while let Some(cell) = queue.pop_front() {
+        let (x, y) = ((cell / cols) as i32, (cell % cols) as i32);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= grid.rows as i32 || ny >= cols as i32 {
+                    continue;
+                }
+                let neighbour = nx as usize * cols + ny as usize;
+                if !seen[neighbour] && usable(neighbour) {
+                    seen[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+    seen[end]
+}
+
+// The highest floor at which the two cells are still connected
+fn reference(
+    grid: &Grid,
+    from: (usize, usize),
+    to: (usize, usize),
+    inside: &dyn Fn(usize) -> bool,
+) -> Option<i32> {
+    let mut levels = grid.data.clone();
+    levels.sort_unstable();
+    levels.dedup();
+    levels
+        .into_iter()
+        .rev()
+        .find(|&floor| connected(grid, from, to, floor, inside))
+}
+
+#[test]
+fn saddles_match_reference() {
+    let mut rng = Rng::new(50);
+    for case in 0..3000 {
+        let grid = random_grid(&mut rng, -5..=12);
+        let tree = merges(&grid, ProminenceOptions::new());
+        let (from, to) = (random_cell(&mut rng, &grid), random_cell(&mut rng, &grid));
+        let saddle = bottleneck(&tree, &grid, from, to).unwrap();
+        let message = format!("case {}: {:?} to {:?} in {:?}", case, from, to, grid);
+        assert_eq!(
+            Some(saddle.elevation),
+            reference(&grid, from, to, &|_| true),
+            "{}",
+            message
+        );
+
+        // The saddle lies on a route that descends no lower
+        let cell = (saddle.row, saddle.col);
+        assert_eq!(grid.data[cell.0 * grid.cols + cell.1], saddle.elevation);
+        assert!(
+            connected(&grid, from, cell, saddle.elevation, &|_| true),
+            "{}",
+            message
+        );
+        assert!(
+            connected(&grid, cell, to, saddle.elevation, &|_| true),
+            "{}",
+            message
+        );
+        assert_eq!(
+            bottleneck(&tree, &grid, to, from),
+            Some(saddle),
+            "{}",
+            message
+        );
+
+        let path = ridge_path(&grid, from, to, saddle.elevation).unwrap();
+        assert_eq!((path[0], path[path.len() - 1]), (from, to), "{}", message);
+        for step in path.windows(2) {
+            let ((r0, c0), (r1, c1)) = (step[0], step[1]);
+            assert!(r0.abs_diff(r1) <= 1 && c0.abs_diff(c1) <= 1 && step[0] != step[1]);
+        }
+        let lowest = path
+            .iter()
+            .map(|&(r, c)| grid.data[r * grid.cols + c])
+            .min();
+        assert_eq!(lowest, Some(saddle.elevation), "{}", message);
+        assert_eq!(
+            ridge_path(&grid, from, to, saddle.elevation + 1),
+            None,
+            "{}",
+            message
+        );
+    }
+}
+
+#[test]
+fn saddles_within_region() {
+    let mut rng = Rng::new(500);
+    for case in 0..2000 {
+        let grid = random_grid(&mut rng, -5..=12);
+        let mut mask = Mask::new(grid.rows, grid.cols);
+        for cell in 0..grid.len() {
+            if rng.range(0, 3) > 0 {
+                mask.insert(cell);
+            }
+        }
+        let inside = |cell: usize| mask.contains(cell);
+        let tree = merges(&grid, ProminenceOptions::new().within(mask.clone()));
+        let (from, to) = (random_cell(&mut rng, &grid), random_cell(&mut rng, &grid));
+        assert_eq!(
+            bottleneck(&tree, &grid, from, to).map(|saddle| saddle.elevation),
+            reference(&grid, from, to, &inside),
+            "case {}: {:?} to {:?} in {:?} within {:?}",
+            case,
+            from,
+            to,
+            grid,
+            mask
+        );
+    }
+}
+
+#[test]
+fn bottleneck_command() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+            .args(["bottleneck", "simple5x5.csv", "-q"])
+            .args(args)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .unwrap()
+    };
+    let stdout = |args: &[&str]| {
+        let output = run(args);
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(
+        stdout(&["--from", "0,4", "--to", "2,1"]),
+        "From:    row 0, col 4, 80 m\n\
+         To:      row 2, col 1, 100 m\n\
+         Saddle:  row 3, col 3, 50 m\n"
+    );
+    assert_eq!(
+        stdout(&["--from", "0,0", "--to", "1,0", "--path", "--format", "json"]),
+        "{\n  \
+         \"from\": {\"row\": 0, \"col\": 0, \"elevation\": 70},\n  \
+         \"to\": {\"row\": 1, \"col\": 0, \"elevation\": 6},\n  \
+         \"saddle\": {\"row\": 1, \"col\": 0, \"elevation\": 6},\n  \
+         \"path\": [\n    \
+         {\"row\": 0, \"col\": 0, \"elevation\": 70},\n    \
+         {\"row\": 1, \"col\": 0, \"elevation\": 6}\n  \
+         ]\n}\n"
+    );
+    assert_eq!(
+        run(&["--from", "0,5", "--to", "1,1"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        run(&["--from", "0.5,-0.5", "--to", "1,1", "--lonlat"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(run(&["--from", "0,0"]).status.code(), Some(2));
+
+    // Points given in lon/lat fall in the cell around them
+    let georef = Georef {
+        west: 10.0,
+        north: 47.0,
+        cell_width: 0.5,
+        cell_height: 0.25,
+    };
+    assert_eq!(georef.cell_at(10.7, 46.6, 5, 5), Some((1, 1)));
+    assert_eq!(georef.cell_at(10.0, 47.0, 5, 5), Some((0, 0)));
+    assert_eq!(georef.cell_at(9.9, 46.6, 5, 5), None);
+    assert_eq!(georef.cell_at(10.7, 45.7, 5, 5), None);
+}
@@ -0,0 +1,51 @@
+// Fixtures shared by the integration tests. Each test crate uses only some
+// of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use topographic_prominence::synth::Rng;
+use topographic_prominence::Grid;
+
+// Grid of 1 to 10 rows and columns with elevations uniform in `values`
+pub fn random_grid(rng: &mut Rng, values: RangeInclusive<i32>) -> Grid {
+    let rows = rng.range(1, 10) as usize;
+    let cols = rng.range(1, 10) as usize;
+    random_grid_sized(rng, rows, cols, values)
+}
+
+// `rows` x `cols` grid with elevations uniform in `values`
+pub fn random_grid_sized(
+    rng: &mut Rng,
+    rows: usize,
+    cols: usize,
+    values: RangeInclusive<i32>,
+) -> Grid {
+    let data = (0..rows * cols)
+        .map(|_| rng.range(*values.start(), *values.end()))
+        .collect();
+    Grid::new(rows, cols, data).unwrap()
+}
+
+// Rows `row..row + rows` and columns `col..col + cols` of `grid`
+pub fn crop(grid: &Grid, (row, col): (usize, usize), (rows, cols): (usize, usize)) -> Grid {
+    let data = (row..row + rows)
+        .flat_map(|r| grid.data[r * grid.cols + col..r * grid.cols + col + cols].to_vec())
+        .collect();
+    Grid::new(rows, cols, data).unwrap()
+}
+
+// Empty scratch directory for one test
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// `name` inside `dir`, as the string paths the library takes
+pub fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_str().unwrap().to_string()
+}
@@ -0,0 +1,134 @@
+// Result diffs: matching against an exhaustive search, and the result
+// readers' handling of malformed files.
+
+use topographic_prominence::diff::{diff_peaks, read_results, DiffOptions};
+use topographic_prominence::json::{self, Json};
+use topographic_prominence::synth::Rng;
+use topographic_prominence::{Error, Peak};
+
+// Matching by position agrees with trying every candidate, for tolerances
+// from none to far beyond the grid
+#[test]
+fn diff_matches_exhaustive_search() {
+    let mut rng = Rng::new(39);
+    let random_peaks = |rng: &mut Rng, count: usize| {
+        (0..count)
+            .map(|_| Peak {
+                prominence: rng.range(1, 5),
+                peak_x: rng.range(0, 60) as usize,
+                peak_y: rng.range(0, 60) as usize,
+                peak_elevation: 100,
+                col_x: None,
+                col_y: None,
+                col_elevation: None,
+                edge_affected: false,
+            })
+            .collect::<Vec<Peak>>()
+    };
+    for case in 0..300 {
+        let reference = random_peaks(&mut rng, 40);
+        let candidate = random_peaks(&mut rng, 40);
+        let tolerance = [0, 1, 3, 10, 100_000, usize::MAX][case % 6];
+        let options = DiffOptions {
+            tolerance,
+            ..DiffOptions::default()
+        };
+        let diff = diff_peaks(&reference, &candidate, &options);
+
+        let mut taken = vec![false; candidate.len()];
+        let mut matched = Vec::new();
+        for peak in &reference {
+            let best = (0..candidate.len())
+                .filter(|&i| !taken[i])
+                .map(|i| {
+                    let other = &candidate[i];
+                    let distance = peak
+                        .peak_x
+                        .abs_diff(other.peak_x)
+                        .max(peak.peak_y.abs_diff(other.peak_y));
+                    (distance, (other.prominence - peak.prominence).abs(), i)
+                })
+                .filter(|&(distance, _, _)| distance <= tolerance)
+                .min();
+            if let Some((_, _, i)) = best {
+                taken[i] = true;
+                matched.push((*peak, candidate[i]));
+            }
+        }
+        let found: Vec<(Peak, Peak)> = diff
+            .matches
+            .iter()
+            .map(|m| (m.reference, m.candidate))
+            .collect();
+        assert_eq!(found, matched, "case {} tolerance {}", case, tolerance);
+        assert_eq!(
+            diff.missing.len() + diff.matches.len(),
+            reference.len(),
+            "case {}",
+            case
+        );
+    }
+}
+
+#[test]
+fn negative_cell_indices_are_rejected() {
+    let dir = std::env::temp_dir();
+    for (ext, content) in [
+        (
+            "txt",
+            "Peaks by prominence:\n    30     -1      4     80      3      3     50\n",
+        ),
+        (
+            "csv",
+            "prominence,row,col,elevation,col_row,col_col,col_elevation\n30,0,4,80,3,-3,50\n",
+        ),
+        (
+            "json",
+            "{\"peaks\": [{\"prominence\": 30, \"row\": 0, \"col\": -4, \"elevation\": 80}]}",
+        ),
+    ] {
+        let path = dir.join(format!("negative-{}.{}", std::process::id(), ext));
+        std::fs::write(&path, content).unwrap();
+        let result = read_results(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(result, Err(Error::Format { .. })),
+            "{}: {:?}",
+            ext,
+            result
+        );
+    }
+}
+
+// Malformed JSON results are reported with their position, never a panic
+#[test]
+fn json_errors_and_escapes() {
+    let position = |text: &str| match json::parse("peaks.json", text) {
+        Err(Error::Parse { line, column, .. }) => Some((line, column)),
+        _ => None,
+    };
+    assert_eq!(position("{\"peaks\": \"\\é\"}"), Some((1, 12)));
+    assert_eq!(position("{\n  \"é\": \"\\u12x4\"}"), Some((2, 11)));
+    assert_eq!(position("\"\\u+123\""), Some((1, 4)));
+    assert_eq!(position("\"é\\"), Some((1, 3)));
+    let deep = "[".repeat(100_000);
+    assert!(position(&deep).is_some());
+    let nested = format!("{}{}", "[".repeat(100), "]".repeat(100));
+    assert!(json::parse("peaks.json", &nested).is_ok());
+
+    // Surrogate pairs make one character; a lone half cannot be shown
+    let string = |text: &str| match json::parse("peaks.json", text).unwrap() {
+        Json::String(s) => s,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(string("\"\\uD83D\\uDE00 \\u00e9\""), "\u{1f600} é");
+    assert_eq!(string("\"\\ud83d!\""), "\u{fffd}!");
+    assert_eq!(string("\"\\uDE00\\uD83D\\n\""), "\u{fffd}\u{fffd}\n");
+    assert_eq!(string("\"\\uD83D\\u0041\""), "\u{fffd}A");
+
+    let path = std::env::temp_dir().join(format!("escape-{}.json", std::process::id()));
+    std::fs::write(&path, "{\"peaks\": \"\\é\"}").unwrap();
+    let result = read_results(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(Error::Parse { .. })), "{:?}", result);
+}
@@ -0,0 +1,231 @@
+// Divide tree indexes: queries checked against full computations, parents
+// against elevations, and files against damage.
+
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{path, random_grid, scratch};
+use topographic_prominence::divide_tree::{build_divide_tree, file_checksum};
+use topographic_prominence::synth::{self, Rng, Terrain, TerrainOptions};
+use topographic_prominence::{
+    compute_prominence, read_divide_tree, DivideTree, Error, Monitor, ProminenceOptions,
+};
+
+fn queries(rng: &mut Rng) -> Vec<ProminenceOptions> {
+    let mut queries = vec![
+        ProminenceOptions::new(),
+        ProminenceOptions::new()
+            .unlimited()
+            .min_prominence(i32::MIN),
+    ];
+    for _ in 0..4 {
+        let options = ProminenceOptions::new().min_prominence(rng.range(-3, 10));
+        queries.push(match rng.range(0, 2) {
+            0 => options.unlimited(),
+            _ => options.max_peaks(rng.range(0, 12) as usize),
+        });
+    }
+    queries
+}
+
+// Ties between equal prominences come out in the same order too
+#[test]
+fn queries_match_compute() {
+    let mut rng = Rng::new(49);
+    for case in 0..2000 {
+        // Few distinct values, so that flats and equal peaks are common
+        let grid = random_grid(&mut rng, -2..=8);
+        let tree = DivideTree::build(&grid, &Monitor::new()).unwrap();
+        for options in queries(&mut rng) {
+            assert_eq!(
+                tree.peaks(&options),
+                compute_prominence(&grid, &options).peaks,
+                "case {}: {:?} of {:?}",
+                case,
+                options,
+                grid
+            );
+        }
+    }
+    for (i, terrain) in Terrain::ALL.iter().enumerate() {
+        let grid = synth::generate(&TerrainOptions {
+            terrain: *terrain,
+            rows: 60,
+            cols: 80,
+            seed: i as u64,
+            ..TerrainOptions::default()
+        })
+        .unwrap();
+        let tree = DivideTree::build(&grid, &Monitor::new()).unwrap();
+        for options in queries(&mut rng) {
+            assert_eq!(
+                tree.peaks(&options),
+                compute_prominence(&grid, &options).peaks,
+                "{:?}: {:?}",
+                terrain,
+                options
+            );
+        }
+    }
+}
+
+// Every peak but the highest hangs from higher ground through its key col
+#[test]
+fn parents_are_higher() {
+    let mut rng = Rng::new(490);
+    for case in 0..2000 {
+        let grid = random_grid(&mut rng, -2..=8);
+        let tree = DivideTree::build(&grid, &Monitor::new()).unwrap();
+        let highest = grid.data.iter().copied().max().unwrap();
+        for node in &tree.nodes {
+            let peak = &node.peak;
+            assert_eq!(node.parent.is_some(), peak.col_elevation.is_some());
+            match node.parent {
+                Some((row, col)) => {
+                    let parent = grid.data[row * grid.cols + col];
+                    assert!(parent >= peak.peak_elevation, "case {}: {:?}", case, grid);
+                    assert!(peak.col_elevation.unwrap() <= peak.peak_elevation);
+                }
+                None => assert_eq!(peak.peak_elevation, highest, "case {}", case),
+            }
+        }
+    }
+
+    let grid = topographic_prominence::read_grid("simple5x5.csv").unwrap();
+    let tree = DivideTree::build(&grid, &Monitor::new()).unwrap();
+    let node = tree.peak_at(0, 4).unwrap();
+    assert_eq!(node.peak.prominence, 30);
+    assert_eq!(node.parent, Some((2, 1)));
+    assert_eq!(tree.peak_at(2, 1).unwrap().parent, None);
+    assert!(tree.peak_at(1, 1).is_none());
+}
+
+#[test]
+fn files_round_trip() {
+    let dir = scratch("divide-tree-round-trip");
+    let grid_path = path(&dir, "grid.asc");
+    let grid = synth::generate(&TerrainOptions {
+        rows: 40,
+        cols: 50,
+        ..TerrainOptions::default()
+    })
+    .unwrap();
+    topographic_prominence::write_grid(&grid_path, &grid, None, None).unwrap();
+    let tree = build_divide_tree(&grid_path, &Monitor::new()).unwrap();
+    assert_eq!(tree.input, grid_path);
+    assert_eq!(
+        (tree.input_size, tree.checksum),
+        file_checksum(&grid_path).unwrap()
+    );
+    assert!(tree.matches_input(&grid_path).unwrap());
+
+    let index = path(&dir, "grid.tree");
+    tree.write(&index).unwrap();
+    let read = read_divide_tree(&index).unwrap();
+    assert_eq!(read, tree);
+
+    // Damage anywhere is caught by the trailing checksum
+    let bytes = fs::read(&index).unwrap();
+    for at in [0, 9, 40, bytes.len() / 2, bytes.len() - 1] {
+        let mut damaged = bytes.clone();
+        damaged[at] ^= 0x10;
+        fs::write(&index, damaged).unwrap();
+        assert!(matches!(
+            read_divide_tree(&index),
+            Err(Error::Format { .. })
+        ));
+    }
+    fs::write(&index, &bytes[..bytes.len() - 30]).unwrap();
+    assert!(matches!(
+        read_divide_tree(&index),
+        Err(Error::Format { .. })
+    ));
+    fs::write(&index, "prominence,row,col\n").unwrap();
+    assert!(matches!(
+        read_divide_tree(&index),
+        Err(Error::Format { .. })
+    ));
+
+    // Elevations whose difference does not fit a prominence
+    let mut extreme = tree.clone();
+    let node = extreme
+        .nodes
+        .iter_mut()
+        .find(|node| node.parent.is_some())
+        .unwrap();
+    node.peak.peak_elevation = i32::MIN;
+    node.peak.col_elevation = Some(1);
+    extreme.write(&index).unwrap();
+    assert!(matches!(
+        read_divide_tree(&index),
+        Err(Error::Format { .. })
+    ));
+
+    // The grid changed since
+    fs::write(&grid_path, "1,2\n3,4\n").unwrap();
+    assert!(!tree.matches_input(&grid_path).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn query_command() {
+    let dir = scratch("divide-tree-command");
+    let index = path(&dir, "simple5x5.tree");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+            .args(args)
+            .arg("-q")
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .unwrap()
+    };
+    let stdout = |args: &[&str]| {
+        let output = run(args);
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(stdout(&["index", "simple5x5.csv", &index]).contains("indexed"));
+    for extra in [
+        &[][..],
+        &["--format", "csv", "--min-prominence", "3"],
+        &["--format", "json", "--max-peaks", "2"],
+        &["--format", "geojson", "--max-peaks", "all"],
+    ] {
+        let mut compute = vec!["compute", "simple5x5.csv"];
+        compute.extend(extra);
+        let mut query = vec!["query", &index];
+        query.extend(extra);
+        assert_eq!(stdout(&query), stdout(&compute), "{:?}", extra);
+    }
+
+    let peak = stdout(&[
+        "query",
+        &index,
+        "--peak",
+        "0,4",
+        "--verify",
+        "simple5x5.csv",
+    ]);
+    assert_eq!(
+        peak,
+        "Summit:     row 0, col 4, 80 m\n\
+         Prominence: 30 m\n\
+         Key col:    row 3, col 3, 50 m\n\
+         Parent:     row 2, col 1, 100 m\n"
+    );
+    assert_eq!(
+        run(&["query", &index, "--peak", "1,1"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        run(&["query", &index, "--verify", "Cargo.toml"])
+            .status
+            .code(),
+        Some(4)
+    );
+    assert_eq!(run(&["query", "simple5x5.csv"]).status.code(), Some(4));
+    fs::remove_dir_all(&dir).unwrap();
+}
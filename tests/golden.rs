@@ -0,0 +1,287 @@
+// Regression tests against the bundled fixtures and small hand-built grids.
+//
+// Conventions pinned here: a peak must be strictly higher than all eight
+// neighbours, so flat summits are not peaks; the highest peak (the first in
+// row-major order among equals) has its elevation as prominence and no key
+// col; other peaks are reported only above sea level.
+
+use std::path::Path;
+use std::process::Command;
+
+use topographic_prominence::diff::{diff_peaks, read_results, DiffOptions};
+use topographic_prominence::output::{write_peaks, OutputFormat, RunInfo};
+use topographic_prominence::{
+    compute_prominence, compute_prominence_with, read_grid, CompactGrid, Elevations, Error, Grid,
+    Monitor, Peak, ProminenceOptions, Samples,
+};
+
+fn fixture(name: &str) -> String {
+    format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn read_fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture(name)).unwrap()
+}
+
+// Grid from rows of whitespace-separated elevations
+fn grid(text: &str) -> Grid {
+    let lines: Vec<Vec<i32>> = text
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|v| v.parse().unwrap())
+                .collect()
+        })
+        .filter(|row: &Vec<i32>| !row.is_empty())
+        .collect();
+    let cols = lines[0].len();
+    Grid::new(lines.len(), cols, lines.concat()).unwrap()
+}
+
+// (prominence, row, col, elevation, key col as (row, col, elevation))
+type Summary = (i32, usize, usize, i32, Option<(usize, usize, i32)>);
+
+// Every peak of `grid`, strongest first
+fn peaks(grid: &Grid) -> Vec<Summary> {
+    let mut peaks: Vec<_> = compute_prominence(grid, &ProminenceOptions::new().unlimited())
+        .peaks
+        .iter()
+        .map(|p| {
+            let col = match (p.col_x, p.col_y, p.col_elevation) {
+                (Some(x), Some(y), Some(e)) => Some((x, y, e)),
+                _ => None,
+            };
+            (p.prominence, p.peak_x, p.peak_y, p.peak_elevation, col)
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.0.cmp(&a.0).then(b.3.cmp(&a.3)).then(a.1.cmp(&b.1)));
+    peaks
+}
+
+fn render(peaks: &[Peak], format: OutputFormat, grid: &Grid) -> String {
+    let info = RunInfo {
+        input: "golden",
+        rows: grid.rows,
+        cols: grid.cols,
+        max_peaks: None,
+        min_prominence: 0,
+        georef: None,
+        window: None,
+    };
+    let mut out = Vec::new();
+    write_peaks(&mut out, format, peaks, &info).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn run_cli(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+        .args(args)
+        .arg("-q")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?} failed: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn simple5x5_csv_matches_golden_results() {
+    let grid = read_grid(&fixture("simple5x5.csv")).unwrap();
+    let result = compute_prominence(&grid, &ProminenceOptions::new());
+    assert_eq!(
+        render(&result.peaks, OutputFormat::Text, &grid),
+        read_fixture("simple5x5_results.txt")
+    );
+}
+
+#[test]
+fn simple5x5_bin_matches_golden_results() {
+    let grid = read_grid(&fixture("simple5x5_dem.bin")).unwrap();
+    assert_eq!((grid.rows, grid.cols), (5, 5));
+    let result = compute_prominence(&grid, &ProminenceOptions::new());
+    assert_eq!(
+        render(&result.peaks, OutputFormat::Text, &grid),
+        read_fixture("simple5x5_results.txt")
+    );
+}
+
+#[test]
+fn cli_output_matches_golden_results() {
+    let expected = read_fixture("simple5x5_results.txt");
+    assert_eq!(run_cli(&["simple5x5.csv"]), expected);
+    assert_eq!(run_cli(&["compute", "simple5x5.csv"]), expected);
+    assert_eq!(run_cli(&["compute", "simple5x5_dem.bin"]), expected);
+}
+
+#[test]
+fn csv_and_json_results_agree_with_golden_text() {
+    let reference = read_results(&fixture("simple5x5_results.txt")).unwrap();
+    let grid = read_grid(&fixture("simple5x5.csv")).unwrap();
+    let peaks = compute_prominence(&grid, &ProminenceOptions::new()).peaks;
+    let dir = std::env::temp_dir();
+    for (format, ext) in [(OutputFormat::Csv, "csv"), (OutputFormat::Json, "json")] {
+        let path = dir.join(format!("golden-{}.{}", std::process::id(), ext));
+        std::fs::write(&path, render(&peaks, format, &grid)).unwrap();
+        let candidate = read_results(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(candidate, reference, "{} results differ", ext);
+    }
+}
+
+#[test]
+fn w100n40_reference_results_are_consistent() {
+    let peaks = read_results(&fixture("W100N40-result.txt")).unwrap();
+    assert_eq!(peaks.len(), 100);
+    assert_eq!(peaks[0].col_elevation, None);
+    assert_eq!(peaks[0].prominence, peaks[0].peak_elevation);
+    for pair in peaks.windows(2) {
+        assert!(pair[0].prominence >= pair[1].prominence);
+    }
+    for peak in &peaks[1..] {
+        let col = peak.col_elevation.unwrap();
+        assert_eq!(peak.prominence, peak.peak_elevation - col);
+    }
+}
+
+// The W100N40 tile itself (GTOPO30, 6000x4800 cells) is too large to bundle;
+// drop W100N40.bin next to its .pat header to run the full comparison.
+#[test]
+fn w100n40_tile_matches_reference_results() {
+    let tile = fixture("W100N40.bin");
+    if !Path::new(&tile).exists() {
+        eprintln!("skipping: {} not present", tile);
+        return;
+    }
+    let reference = read_results(&fixture("W100N40-result.txt")).unwrap();
+    let grid = read_grid(&tile).unwrap();
+    let peaks = compute_prominence(&grid, &ProminenceOptions::new()).peaks;
+    let diff = diff_peaks(&reference, &peaks, &DiffOptions::default());
+    assert!(
+        diff.is_empty(),
+        "{} changed, {} missing, {} extra",
+        diff.changed().count(),
+        diff.missing.len(),
+        diff.extra.len()
+    );
+}
+
+#[test]
+fn single_cell_is_the_highest_peak() {
+    assert_eq!(peaks(&grid("42")), [(42, 0, 0, 42, None)]);
+    assert_eq!(peaks(&grid("0")), [(0, 0, 0, 0, None)]);
+    // Negative prominence falls below the default minimum of zero
+    assert_eq!(peaks(&grid("-3")), []);
+}
+
+#[test]
+fn single_row_and_column() {
+    assert_eq!(
+        peaks(&grid("1 6 2 3 1")),
+        [(6, 0, 1, 6, None), (1, 0, 3, 3, Some((0, 2, 2)))]
+    );
+    assert_eq!(
+        peaks(&grid("1\n6\n2\n3\n1")),
+        [(6, 1, 0, 6, None), (1, 3, 0, 3, Some((2, 0, 2)))]
+    );
+}
+
+#[test]
+fn plateau_summit_is_not_a_peak() {
+    let terrain = grid(
+        "0 0 0 0 0
+         0 5 5 0 0
+         0 5 5 0 9
+         0 0 0 0 0",
+    );
+    assert_eq!(peaks(&terrain), [(9, 2, 4, 9, None)]);
+}
+
+#[test]
+fn twin_peaks_of_equal_height() {
+    let terrain = grid(
+        "1 1 1 1 1
+         1 8 2 8 1
+         1 1 1 1 1",
+    );
+    // The first summit in row-major order is the highest
+    assert_eq!(
+        peaks(&terrain),
+        [(8, 1, 1, 8, None), (6, 1, 3, 8, Some((1, 2, 2)))]
+    );
+}
+
+#[test]
+fn key_col_on_the_grid_edge() {
+    let terrain = grid(
+        "7 4 2 4 6
+         4 3 0 3 4
+         1 1 0 1 1",
+    );
+    assert_eq!(
+        peaks(&terrain),
+        [(7, 0, 0, 7, None), (4, 0, 4, 6, Some((0, 2, 2)))]
+    );
+}
+
+// Grids too large to number their cells with u32 are refused, not swept
+#[test]
+fn oversized_grids_are_rejected() {
+    struct Huge;
+    impl Elevations for Huge {
+        fn dimensions(&self) -> (usize, usize) {
+            (1 << 16, 1 << 16)
+        }
+        fn metres(&self, _: usize) -> i32 {
+            0
+        }
+        fn key(&self, _: usize) -> u32 {
+            0
+        }
+    }
+    let run = compute_prominence_with(&Huge, &ProminenceOptions::default(), &Monitor::new());
+    assert!(matches!(run, Err(Error::InvalidParameter(_))));
+    assert!(matches!(
+        Grid::new(1 << 16, 1 << 16, Vec::new()),
+        Err(Error::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        CompactGrid::new(usize::MAX, 2, Samples::I16(Vec::new())),
+        Err(Error::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn below_sea_level_terrain() {
+    let terrain = grid(
+        "-9 -9 -9 -9 -9
+          3 -4 -3 -6  2
+         -9 -9 -9 -9 -9",
+    );
+    // The summit at -3 is below sea level and not reported; the col of the
+    // peak at 2 lies below sea level, so its prominence exceeds its height.
+    assert_eq!(
+        peaks(&terrain),
+        [(8, 1, 4, 2, Some((1, 3, -6))), (3, 1, 0, 3, None)]
+    );
+}
+
+#[test]
+fn lower_summit_merged_into_smaller_set() {
+    assert_eq!(
+        peaks(&grid("1 4 2 5 3")),
+        [(5, 0, 3, 5, None), (2, 0, 1, 4, Some((0, 2, 2)))]
+    );
+    let terrain = grid(
+        "1 1 1 1 1 1 1
+         1 5 3 6 3 4 1
+         1 1 1 1 1 1 1",
+    );
+    assert_eq!(
+        peaks(&terrain),
+        [
+            (6, 1, 3, 6, None),
+            (2, 1, 1, 5, Some((1, 2, 3))),
+            (1, 1, 5, 4, Some((1, 4, 3)))
+        ]
+    );
+}
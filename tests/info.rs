@@ -0,0 +1,31 @@
+// The info command's summary of grids spanning the whole i32 range.
+
+use std::process::Command;
+
+#[test]
+fn info_on_extreme_elevations() {
+    let path = std::env::temp_dir().join(format!("info-{}.csv", std::process::id()));
+    std::fs::write(&path, "-2000000000,2000000000\n-2147483648,2147483647\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+        .args(["info", path.to_str().unwrap(), "--bins", "4", "-q"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Elevation:  min -2147483648, max 2147483647"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("-2147483648 .. -1073741825          2"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("1073741824 .. 2147483647          2"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,54 @@
+// Map and table products of the compute command written next to the peaks.
+
+use std::process::{Command, Output};
+
+fn compute(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+        .args(["compute", "simple5x5.csv", "-q"])
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+// Failed writes surface as I/O errors rather than being lost with the buffer
+#[cfg(target_os = "linux")]
+#[test]
+fn product_write_errors_are_reported() {
+    for product in ["--domain-stats", "--islands"] {
+        let output = compute(&[product, "/dev/full"]);
+        assert_eq!(output.status.code(), Some(3), "{}: {:?}", product, output);
+    }
+}
+
+// A highest summit below sea level has a negative prominence, shown as 0
+#[test]
+fn negative_prominence_is_clamped_in_rasters() {
+    let dir = std::env::temp_dir();
+    let grid = dir.join(format!("products-{}.csv", std::process::id()));
+    let raster = dir.join(format!("products-{}-prominence.csv", std::process::id()));
+    std::fs::write(&grid, "-9,-9,-9\n-9,-5,-9\n-9,-9,-9\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+        .args([
+            "compute",
+            grid.to_str().unwrap(),
+            "-q",
+            "--min-prominence",
+            "-100",
+        ])
+        .args(["--prominence-raster", raster.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let values = std::fs::read_to_string(&raster).unwrap();
+    std::fs::remove_file(&grid).unwrap();
+    std::fs::remove_file(&raster).unwrap();
+    assert_eq!(values, "0,0,0\n0,0,0\n0,0,0\n");
+}
+
+// A zero render size is rejected before the extension or any pixels
+#[test]
+fn zero_render_size_is_rejected_first() {
+    let output = compute(&["--render", "map.gif", "--render-size", "0"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
@@ -0,0 +1,212 @@
+// Property tests: the union-find sweep against the brute-force reference on
+// random small grids. Grids come from a fixed-seed generator so failures
+// reproduce; the failing seed and grid are printed.
+
+use std::ops::ControlFlow;
+
+mod common;
+
+use common::random_grid_sized;
+use topographic_prominence::island::{label_islands, trace_islands};
+use topographic_prominence::reference::reference_prominence;
+use topographic_prominence::synth::Rng;
+use topographic_prominence::{
+    compute_prominence, for_each_peak, CompactGrid, Grid, Peak, ProminenceOptions, Samples,
+};
+
+const CASES: u64 = 2000;
+
+// Grid of up to 8x8 cells. Narrow elevation ranges give many ties, plateaus
+// and cells below sea level; wide ones mostly distinct elevations.
+fn random_grid(seed: u64) -> Grid {
+    let mut rng = Rng::new(seed);
+    let rows = rng.range(1, 8) as usize;
+    let cols = rng.range(1, 8) as usize;
+    let values = match rng.range(0, 2) {
+        0 => -2..=3,
+        1 => 0..=9,
+        _ => -50..=500,
+    };
+    random_grid_sized(&mut rng, rows, cols, values)
+}
+
+fn options() -> ProminenceOptions {
+    ProminenceOptions::new()
+        .unlimited()
+        .min_prominence(i32::MIN)
+}
+
+// Peaks in the reference's order
+fn sorted(mut peaks: Vec<Peak>) -> Vec<Peak> {
+    peaks.sort_by_key(|p| {
+        (
+            std::cmp::Reverse(p.prominence),
+            std::cmp::Reverse(p.peak_elevation),
+            p.peak_x,
+            p.peak_y,
+        )
+    });
+    peaks
+}
+
+fn check<F: Fn(&Grid) -> Vec<Peak>>(what: &str, sweep: F) {
+    for seed in 0..CASES {
+        let grid = random_grid(seed);
+        let expected = reference_prominence(&grid);
+        let actual = sorted(sweep(&grid));
+        assert_eq!(
+            actual, expected,
+            "{} differs from the reference for seed {} on {}x{} grid {:?}",
+            what, seed, grid.rows, grid.cols, grid.data
+        );
+    }
+}
+
+#[test]
+fn compute_prominence_matches_reference() {
+    check("compute_prominence", |grid| {
+        compute_prominence(grid, &options()).peaks
+    });
+}
+
+#[test]
+fn for_each_peak_matches_reference() {
+    check("for_each_peak", |grid| {
+        let mut peaks = Vec::new();
+        for_each_peak(grid, &options(), |peak| {
+            peaks.push(peak);
+            ControlFlow::Continue(())
+        });
+        peaks
+    });
+}
+
+// Without open edges or a mask the highest peak streams first
+#[test]
+fn for_each_peak_starts_with_the_highest_peak() {
+    for seed in 0..CASES {
+        let grid = random_grid(seed);
+        let mut peaks = Vec::new();
+        for_each_peak(&grid, &options(), |peak| {
+            peaks.push(peak);
+            ControlFlow::Continue(())
+        });
+        let highest = peaks.iter().position(|peak| peak.col_elevation.is_none());
+        assert!(highest.is_none_or(|i| i == 0), "seed {}: {:?}", seed, peaks);
+    }
+}
+
+#[test]
+fn tracked_merges_match_reference() {
+    check("compute_prominence with merges", |grid| {
+        compute_prominence(grid, &options().track_merges(true)).peaks
+    });
+}
+
+#[test]
+fn compact_grids_match_reference() {
+    check("compute_prominence on i16 samples", |grid| {
+        let compact = CompactGrid::from_grid(grid);
+        assert!(matches!(compact.samples, Samples::I16(_)));
+        compute_prominence(&compact, &options()).peaks
+    });
+    check("compute_prominence on i32 samples", |grid| {
+        let compact = CompactGrid::new(grid.rows, grid.cols, Samples::I32(grid.data.clone()));
+        compute_prominence(&compact.unwrap(), &options()).peaks
+    });
+    check("compute_prominence on f32 samples", |grid| {
+        let samples = grid.data.iter().map(|&v| v as f32).collect();
+        let compact = CompactGrid::new(grid.rows, grid.cols, Samples::F32(samples));
+        compute_prominence(&compact.unwrap(), &options()).peaks
+    });
+}
+
+#[test]
+fn u16_samples_match_reference() {
+    for seed in 0..CASES {
+        let grid = random_grid(seed);
+        let data: Vec<i32> = grid.data.iter().map(|&v| v.max(0) * 100).collect();
+        let grid = Grid::new(grid.rows, grid.cols, data).unwrap();
+        let samples = grid.data.iter().map(|&v| v as u16).collect();
+        let compact = CompactGrid::new(grid.rows, grid.cols, Samples::U16(samples)).unwrap();
+        assert_eq!(
+            sorted(compute_prominence(&compact, &options()).peaks),
+            reference_prominence(&grid),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn summits_lie_in_their_own_island() {
+    for seed in 0..CASES {
+        let grid = random_grid(seed);
+        let result = compute_prominence(&grid, &options().track_merges(true));
+        let labels = label_islands(result.merges.as_ref().unwrap(), &grid, &result.peaks);
+        for (i, peak) in result.peaks.iter().enumerate() {
+            let summit = peak.peak_x * grid.cols + peak.peak_y;
+            assert_eq!(
+                labels[summit] as usize,
+                i + 1,
+                "summit of peak {} mislabelled for seed {} on {:?}",
+                i + 1,
+                seed,
+                grid.data
+            );
+        }
+    }
+}
+
+// Even-odd test of `p` against a closed ring
+fn in_ring(p: (f64, f64), ring: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let (a, b) = (edge[0], edge[1]);
+        if (a.0 > p.0) != (b.0 > p.0) && p.1 < a.1 + (p.0 - a.0) / (b.0 - a.0) * (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[test]
+fn island_polygons_enclose_their_summits() {
+    for seed in 0..CASES {
+        let grid = random_grid(seed);
+        let result = compute_prominence(&grid, &options().track_merges(true));
+        let tree = result.merges.as_ref().unwrap();
+        let labels = label_islands(tree, &grid, &result.peaks);
+        let islands = trace_islands(tree, &grid, &result.peaks, &labels, None);
+        for (island, peak) in islands.iter().zip(&result.peaks) {
+            let summit = (peak.peak_x as f64, peak.peak_y as f64);
+            let rings = island.polygons.iter().flatten();
+            assert!(rings.clone().all(|ring| ring.first() == ring.last()));
+            let enclosing = island
+                .polygons
+                .iter()
+                .filter(|rings| {
+                    in_ring(summit, &rings[0])
+                        && !rings[1..].iter().any(|hole| in_ring(summit, hole))
+                })
+                .count();
+            assert_eq!(
+                enclosing, 1,
+                "island of peak {} for seed {} on {:?}",
+                island.rank, seed, grid.data
+            );
+        }
+    }
+}
+
+#[test]
+fn reference_on_hand_checked_grid() {
+    let grid = Grid::new(3, 5, vec![1, 1, 1, 1, 1, 1, 4, 2, 5, 1, 1, 1, 1, 1, 1]).unwrap();
+    let peaks = reference_prominence(&grid);
+    assert_eq!(peaks.len(), 2);
+    assert_eq!((peaks[0].prominence, peaks[0].col_elevation), (5, None));
+    assert_eq!(
+        (peaks[1].prominence, peaks[1].col_x, peaks[1].col_y),
+        (2, Some(1), Some(2))
+    );
+}
@@ -0,0 +1,265 @@
+// Region masks: polygon parsing, rasterisation, and sweeps confined to a
+// region checked against sweeps of the equivalent cropped grids.
+
+use std::process::Command;
+
+mod common;
+
+use common::{crop, random_grid_sized};
+use topographic_prominence::synth::Rng;
+use topographic_prominence::{compute_prominence, Georef, Mask, Peak, ProminenceOptions, Region};
+
+fn options() -> ProminenceOptions {
+    ProminenceOptions::new().unlimited().min_prominence(1)
+}
+
+fn rectangle(mask: &mut Mask, (row, col): (usize, usize), (rows, cols): (usize, usize)) {
+    for r in row..row + rows {
+        for c in col..col + cols {
+            mask.insert(r * mask.cols + c);
+        }
+    }
+}
+
+fn shifted(peaks: Vec<Peak>, (row, col): (usize, usize)) -> Vec<Peak> {
+    peaks
+        .into_iter()
+        .map(|peak| Peak {
+            peak_x: peak.peak_x + row,
+            peak_y: peak.peak_y + col,
+            col_x: peak.col_x.map(|x| x + row),
+            col_y: peak.col_y.map(|y| y + col),
+            ..peak
+        })
+        .collect()
+}
+
+fn sorted(mut peaks: Vec<Peak>) -> Vec<Peak> {
+    peaks.sort_by_key(|p| (std::cmp::Reverse(p.prominence), p.peak_x, p.peak_y));
+    peaks
+}
+
+#[test]
+fn geojson_regions() {
+    let polygon = r#"{"type": "Polygon", "coordinates": [
+        [[0, 0], [4, 0], [4, 4], [0, 4], [0, 0]],
+        [[1, 1], [2, 1], [2, 2], [1, 2], [1, 1]]]}"#;
+    let region = Region::from_geojson("park.geojson", polygon).unwrap();
+    assert_eq!(region.polygons.len(), 1);
+    assert_eq!(region.polygons[0].len(), 2);
+    assert!(region.contains(3.0, 3.0));
+    assert!(!region.contains(1.5, 1.5));
+    assert!(!region.contains(5.0, 1.0));
+    assert_eq!(region.bounds(), (0.0, 0.0, 4.0, 4.0));
+
+    let collection = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"name": "a"},
+         "geometry": {"type": "MultiPolygon", "coordinates": [
+            [[[0, 0], [1, 0], [1, 1]]], [[[5, 5], [6, 5], [6, 6, 100]]]]}},
+        {"type": "Feature", "properties": null,
+         "geometry": {"type": "Polygon", "coordinates": [[[9, 9], [10, 9], [10, 10]]]}}]}"#;
+    let region = Region::from_geojson("parks.geojson", collection).unwrap();
+    assert_eq!(region.polygons.len(), 3);
+    assert!(region.contains(5.8, 5.5));
+
+    for bad in [
+        r#"{"type": "LineString", "coordinates": [[0, 0], [1, 1]]}"#,
+        r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 1]]]}"#,
+        r#"{"type": "Polygon", "coordinates": [[["a", 0], [1, 1], [1, 0]]]}"#,
+        r#"{"type": "FeatureCollection", "features": []}"#,
+        r#"{"coordinates": []}"#,
+    ] {
+        assert!(Region::from_geojson("bad.geojson", bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn wkt_regions() {
+    let region = Region::from_wkt(
+        "park.wkt",
+        "POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0), (1 1, 2 1, 2 2, 1 1))",
+    )
+    .unwrap();
+    assert_eq!(region.polygons[0].len(), 2);
+
+    let region = Region::from_wkt(
+        "parks.wkt",
+        " multipolygon Z (((0 0 1, 1 0 1, 1 1 1, 0 0 1)),\n((5 5 0, 6 5 0, 6 6 0)))\n",
+    )
+    .unwrap();
+    assert_eq!(region.polygons.len(), 2);
+    assert_eq!(
+        region.polygons[1][0],
+        vec![(5.0, 5.0), (6.0, 5.0), (6.0, 6.0)]
+    );
+
+    for bad in [
+        "POINT (1 2)",
+        "POLYGON ((0 0, 1 0, 1 1)",
+        "POLYGON ((0 0, 1 0, 1 1)) extra",
+        "POLYGON ((0 0, 1 x, 1 1))",
+        "POLYGON ((0 0, 1 0))",
+        "POLYGON (0 0, 1 0, 1 1)",
+        "MULTIPOLYGON ((0 0, 1 0, 1 1))",
+        "MULTIPOLYGON ((((0 0, 1 0, 1 1))))",
+    ] {
+        assert!(Region::from_wkt("bad.wkt", bad).is_err(), "{}", bad);
+    }
+    let deep = format!("POLYGON {}", "(".repeat(100_000));
+    assert!(Region::from_wkt("deep.wkt", &deep).is_err());
+}
+
+// Random star-shaped polygons with holes, rasterised row by row, agree with
+// the point-in-polygon test at every cell centre
+#[test]
+fn mask_matches_point_in_polygon() {
+    let georef = Georef {
+        west: -3.0,
+        north: 47.0,
+        cell_width: 0.25,
+        cell_height: 0.2,
+    };
+    let (rows, cols) = (40, 50);
+    let mut rng = Rng::new(47);
+    for case in 0..200 {
+        let star = |rng: &mut Rng, (x, y): (f64, f64), radius: f64| -> Vec<(f64, f64)> {
+            let points = rng.range(3, 12);
+            (0..points)
+                .map(|i| {
+                    let angle = i as f64 / points as f64 * std::f64::consts::TAU;
+                    let r = radius * (0.3 + 0.7 * rng.unit());
+                    (x + r * angle.cos(), y + r * angle.sin())
+                })
+                .collect()
+        };
+        let polygons = (0..rng.range(1, 3))
+            .map(|_| {
+                let centre = (-3.0 + 12.5 * rng.unit(), 39.0 + 8.0 * rng.unit());
+                let mut polygon = vec![star(&mut rng, centre, 4.0)];
+                if rng.range(0, 1) == 1 {
+                    polygon.push(star(&mut rng, centre, 1.0));
+                }
+                polygon
+            })
+            .collect();
+        let region = Region::new(polygons).unwrap();
+        let mask = region.mask(&georef, rows, cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                let (x, y) = georef.cell_center(row, col);
+                assert_eq!(
+                    mask.contains(row * cols + col),
+                    region.contains(x, y),
+                    "case {} cell ({}, {}) of {:?}",
+                    case,
+                    row,
+                    col,
+                    region
+                );
+            }
+        }
+    }
+}
+
+// A rectangular region is the same as a grid cropped to it
+#[test]
+fn within_rectangle_matches_crop() {
+    let mut rng = Rng::new(470);
+    for case in 0..1000 {
+        let (rows, cols) = (rng.range(1, 9) as usize, rng.range(1, 9) as usize);
+        let grid = random_grid_sized(&mut rng, rows, cols, -5..=40);
+        let row = rng.range(0, rows as i32 - 1) as usize;
+        let col = rng.range(0, cols as i32 - 1) as usize;
+        let size = (
+            rng.range(1, (rows - row) as i32) as usize,
+            rng.range(1, (cols - col) as i32) as usize,
+        );
+        let mut mask = Mask::new(rows, cols);
+        rectangle(&mut mask, (row, col), size);
+
+        let expected = compute_prominence(&crop(&grid, (row, col), size), &options()).peaks;
+        let within = options().within(mask).track_merges(true);
+        let actual = compute_prominence(&grid, &within).peaks;
+        assert_eq!(
+            sorted(actual),
+            sorted(shifted(expected, (row, col))),
+            "case {}: {:?} at ({}, {}) of {}x{} grid {:?}",
+            case,
+            size,
+            row,
+            col,
+            rows,
+            cols,
+            grid.data
+        );
+    }
+}
+
+// Parts of a region apart from each other are separate worlds: each has
+// its own highest peak with no key col
+#[test]
+fn within_detached_parts() {
+    let mut rng = Rng::new(4700);
+    for case in 0..500 {
+        let grid = random_grid_sized(&mut rng, 9, 12, -5..=40);
+        let parts = [((0, 0), (4, 5)), ((5, 2), (4, 3)), ((1, 7), (6, 5))];
+        let mut mask = Mask::new(9, 12);
+        let mut expected = Vec::new();
+        for (origin, size) in parts {
+            rectangle(&mut mask, origin, size);
+            let peaks = compute_prominence(&crop(&grid, origin, size), &options()).peaks;
+            expected.extend(shifted(peaks, origin));
+        }
+        let actual = compute_prominence(&grid, &options().within(mask)).peaks;
+        assert_eq!(
+            sorted(actual),
+            sorted(expected),
+            "case {}: grid {:?}",
+            case,
+            grid.data
+        );
+    }
+}
+
+#[test]
+fn region_options() {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("region-{}.wkt", std::process::id()));
+    // Columns 3 and 4 in grid units, as the grid has no georeference
+    std::fs::write(&path, "POLYGON ((3 0, 5 0, 5 -5, 3 -5, 3 0))").unwrap();
+    let run = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_topographic_prominence"))
+            .args([
+                "compute",
+                "simple5x5.csv",
+                "--format",
+                "csv",
+                "-q",
+                "--region",
+            ])
+            .arg(&path)
+            .args(extra)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let header = "prominence,row,col,elevation,col_row,col_col,col_elevation\n";
+
+    // Summits inside, cols anywhere
+    assert_eq!(
+        run(&[]),
+        format!("{}30,0,4,80,3,3,50\n2,3,4,74,2,4,72\n", header)
+    );
+    assert_eq!(
+        run(&["--max-peaks", "1"]),
+        format!("{}30,0,4,80,3,3,50\n", header)
+    );
+    // The region is the whole world
+    assert_eq!(
+        run(&["--within-region"]),
+        format!("{}80,0,4,80,,,\n2,3,4,74,2,4,72\n", header)
+    );
+    std::fs::remove_file(&path).unwrap();
+}
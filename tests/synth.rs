@@ -0,0 +1,253 @@
+// Stress tests of the sweep on generated terrain: agreement with the
+// reference on small grids, structural invariants on larger ones, and
+// reproducible generation through every writable input format, which keep
+// samples at their native width when read back.
+
+use std::ops::ControlFlow;
+
+use topographic_prominence::reference::reference_prominence;
+use topographic_prominence::source::BinSource;
+use topographic_prominence::synth::{generate, Terrain, TerrainOptions};
+use topographic_prominence::{
+    compute_prominence, for_each_peak, read_compact_grid, read_grid, write_grid, Error, Grid, Peak,
+    ProminenceOptions, Registry, Samples, Window,
+};
+
+fn terrain(terrain: Terrain, rows: usize, cols: usize, seed: u64) -> Grid {
+    generate(&TerrainOptions {
+        terrain,
+        rows,
+        cols,
+        seed,
+        relief: 200,
+        ..TerrainOptions::default()
+    })
+    .unwrap()
+}
+
+fn all_peaks(grid: &Grid) -> Vec<Peak> {
+    compute_prominence(grid, &ProminenceOptions::new().unlimited()).peaks
+}
+
+// Invariants every result must satisfy, whatever the terrain
+fn check_invariants(grid: &Grid, peaks: &[Peak]) {
+    let highest = peaks.iter().filter(|p| p.col_elevation.is_none()).count();
+    assert!(highest <= 1, "{} peaks without a key col", highest);
+    for peak in peaks {
+        let summit = grid.elevation(peak.peak_x, peak.peak_y);
+        assert_eq!(summit, peak.peak_elevation);
+        if let (Some(x), Some(y), Some(col)) = (peak.col_x, peak.col_y, peak.col_elevation) {
+            assert_eq!(grid.elevation(x, y), col);
+            assert_eq!(peak.prominence, peak.peak_elevation - col);
+            assert!(peak.prominence > 0 && peak.peak_elevation > 0);
+        }
+    }
+    for pair in peaks.windows(2) {
+        assert!(pair[0].prominence >= pair[1].prominence);
+    }
+}
+
+#[test]
+fn generated_terrain_matches_reference() {
+    for kind in Terrain::ALL {
+        for seed in 0..5 {
+            let grid = terrain(kind, 13 + seed as usize, 21, seed);
+            let mut peaks = all_peaks(&grid);
+            peaks.sort_by_key(|p| {
+                (
+                    std::cmp::Reverse(p.prominence),
+                    std::cmp::Reverse(p.peak_elevation),
+                    p.peak_x,
+                    p.peak_y,
+                )
+            });
+            assert_eq!(
+                peaks,
+                reference_prominence(&grid),
+                "{} seed {}",
+                kind.name(),
+                seed
+            );
+        }
+    }
+}
+
+#[test]
+fn generated_terrain_keeps_invariants() {
+    for kind in Terrain::ALL {
+        let grid = terrain(kind, 300, 257, 42);
+        let peaks = all_peaks(&grid);
+        check_invariants(&grid, &peaks);
+
+        let mut streamed = 0;
+        for_each_peak(&grid, &ProminenceOptions::new(), |_| {
+            streamed += 1;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(streamed, peaks.len(), "{}", kind.name());
+    }
+}
+
+#[test]
+fn pathological_terrain() {
+    // Terraces are flat, so there are no strict peaks at all
+    assert!(all_peaks(&terrain(Terrain::Staircase, 64, 64, 1)).is_empty());
+
+    // A lone spike is the only peak, however flat the ground around it
+    let spike = all_peaks(&terrain(Terrain::Spike, 64, 64, 1));
+    assert_eq!(spike.len(), 1);
+    assert_eq!((spike[0].prominence, spike[0].col_elevation), (200, None));
+
+    // The mesa is the highest ground but has no summit cell, so every
+    // reported peak gets a key col
+    let plateau = all_peaks(&terrain(Terrain::Plateau, 64, 64, 1));
+    assert!(!plateau.is_empty());
+    assert!(plateau.iter().all(|p| p.col_elevation.is_some()));
+}
+
+#[test]
+fn generation_is_reproducible() {
+    for kind in Terrain::ALL {
+        let a = terrain(kind, 40, 30, 9);
+        assert_eq!(a.data, terrain(kind, 40, 30, 9).data, "{}", kind.name());
+        assert!(a.data.iter().all(|&v| (0..=200).contains(&v)));
+    }
+    assert_ne!(
+        terrain(Terrain::DiamondSquare, 40, 30, 1).data,
+        terrain(Terrain::DiamondSquare, 40, 30, 2).data
+    );
+    assert_ne!(
+        terrain(Terrain::Noise, 40, 30, 1).data,
+        terrain(Terrain::Noise, 40, 30, 2).data
+    );
+}
+
+#[test]
+fn generated_terrain_round_trips_through_every_format() {
+    let grid = terrain(Terrain::Noise, 33, 47, 5);
+    let dir = std::env::temp_dir();
+    for ext in ["csv", "asc", "bin"] {
+        let path = dir.join(format!("synth-{}.{}", std::process::id(), ext));
+        let path = path.to_str().unwrap();
+        write_grid(path, &grid, None, None).unwrap();
+        let read = read_grid(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        if ext == "bin" {
+            std::fs::remove_file(path.replace(".bin", ".pat")).unwrap();
+        }
+        assert_eq!(
+            (read.rows, read.cols, read.data),
+            (33, 47, grid.data.clone())
+        );
+    }
+}
+
+#[test]
+fn binary_samples_read_at_native_width() {
+    let grid = terrain(Terrain::DiamondSquare, 70, 90, 3);
+    let path = std::env::temp_dir().join(format!("synth-samples-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    write_grid(path, &grid, None, None).unwrap();
+    let mut source = BinSource::open(path).unwrap();
+    let mut samples = Vec::new();
+    source.read_samples(10, 50, &mut samples).unwrap();
+    assert!(source.read_samples(60, 11, &mut Vec::new()).is_err());
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(path.replace(".bin", ".pat")).unwrap();
+    let expected: Vec<i16> = grid.data[10 * 90..60 * 90]
+        .iter()
+        .map(|&v| v as i16)
+        .collect();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn fractional_asc_keeps_exact_heights() {
+    let path = std::env::temp_dir().join(format!("synth-fractional-{}.asc", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(
+        path,
+        "ncols 4\nnrows 3\nxllcorner 0\nyllcorner 0\ncellsize 1\n\
+         1 1 1 1\n1 100.4 100.2 1\n1 1 1 1\n",
+    )
+    .unwrap();
+    let registry = Registry::default();
+
+    // Rounded, the two tops are one flat summit; exact, the 100.4 is a peak
+    let grid = registry.open(path).unwrap().read_all().unwrap();
+    assert!(all_peaks(&grid).is_empty());
+    let compact = read_compact_grid(path).unwrap();
+    assert!(matches!(compact.samples, Samples::F32(_)));
+    assert_eq!(compact.to_grid(), grid);
+    let peaks = compute_prominence(&compact, &ProminenceOptions::new().unlimited()).peaks;
+    assert_eq!(
+        peaks
+            .iter()
+            .map(|p| (p.peak_x, p.peak_y, p.peak_elevation))
+            .collect::<Vec<_>>(),
+        [(1, 1, 100)]
+    );
+
+    let window = Window::from_ranges((1, 1), (1, 3), (3, 4)).unwrap();
+    let compact = registry
+        .open(path)
+        .unwrap()
+        .read_compact_window(&window)
+        .unwrap();
+    assert_eq!(compact.samples, Samples::F32(vec![100.4, 100.2, 1.0]));
+
+    // Whole-metre grids stay at the narrowest integer width
+    std::fs::write(
+        path,
+        "ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n3 4.0\n",
+    )
+    .unwrap();
+    let compact = read_compact_grid(path).unwrap();
+    assert_eq!(compact.samples, Samples::I16(vec![3, 4]));
+
+    // Headers promising more cells than the file holds, or than fit in memory
+    for size in [
+        "ncols 3\nnrows 2",
+        "ncols 100000000\nnrows 100000000",
+        "ncols 1e19\nnrows 1e19",
+    ] {
+        std::fs::write(
+            path,
+            format!("{}\nxllcorner 0\nyllcorner 0\ncellsize 1\n3 4\n", size),
+        )
+        .unwrap();
+        let result = registry.open(path);
+        assert!(matches!(result, Err(Error::Format { .. })), "{}", size);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn invalid_terrain_options() {
+    for options in [
+        TerrainOptions {
+            rows: 0,
+            ..TerrainOptions::default()
+        },
+        TerrainOptions {
+            relief: 0,
+            ..TerrainOptions::default()
+        },
+        TerrainOptions {
+            roughness: 1.5,
+            ..TerrainOptions::default()
+        },
+    ] {
+        assert!(generate(&options).is_err());
+    }
+}
+
+// A full-size GTOPO30 tile's worth of cells; run with --ignored
+#[test]
+#[ignore]
+fn full_tile_stress() {
+    let grid = terrain(Terrain::DiamondSquare, 6000, 4800, 1);
+    let peaks = compute_prominence(&grid, &ProminenceOptions::new()).peaks;
+    assert_eq!(peaks.len(), 100);
+    check_invariants(&grid, &peaks);
+}
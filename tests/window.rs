@@ -0,0 +1,250 @@
+// Cropped runs: windows cut from random grids and synthetic terrain, checked
+// against runs on the whole grid.
+
+mod common;
+
+use common::{crop, random_grid};
+use topographic_prominence::reference::reference_prominence;
+use topographic_prominence::synth::{self, Rng, Terrain, TerrainOptions};
+use topographic_prominence::{
+    compute_prominence, write_grid, Edges, Georef, Grid, Mask, Peak, ProminenceOptions, Registry,
+    Window,
+};
+
+fn options() -> ProminenceOptions {
+    ProminenceOptions::new()
+        .unlimited()
+        .min_prominence(i32::MIN)
+}
+
+fn random_window(rng: &mut Rng, grid: &Grid) -> Window {
+    let mut range = |count: usize| {
+        let first = rng.range(0, count as i32 - 1) as usize;
+        let last = rng.range(first as i32, count as i32 - 1) as usize;
+        (first, last)
+    };
+    let rows = range(grid.rows);
+    let cols = range(grid.cols);
+    Window::from_ranges(rows, cols, (grid.rows, grid.cols)).unwrap()
+}
+
+fn crop_window(grid: &Grid, window: &Window) -> Grid {
+    crop(grid, (window.row, window.col), (window.rows, window.cols))
+}
+
+// Peaks of `window` in the full grid's frame
+fn windowed_peaks(grid: &Grid, window: &Window) -> Vec<Peak> {
+    let options = options().open_edges(window.open_edges(grid.rows, grid.cols));
+    let mut peaks = compute_prominence(&crop_window(grid, window), &options).peaks;
+    window.to_grid_frame(&mut peaks);
+    peaks
+}
+
+fn position(peak: &Peak) -> (usize, usize) {
+    (peak.peak_x, peak.peak_y)
+}
+
+#[test]
+fn windowed_peaks_match_reference_on_crop() {
+    let mut rng = Rng::new(46);
+    for case in 0..2000 {
+        let grid = random_grid(&mut rng, -5..=40);
+        let window = random_window(&mut rng, &grid);
+        let mut expected = reference_prominence(&crop_window(&grid, &window));
+        window.to_grid_frame(&mut expected);
+        let mut actual = windowed_peaks(&grid, &window);
+        for peak in &mut actual {
+            peak.edge_affected = false;
+        }
+        actual.sort_by_key(|p| {
+            (
+                std::cmp::Reverse(p.prominence),
+                std::cmp::Reverse(p.peak_elevation),
+                p.peak_x,
+                p.peak_y,
+            )
+        });
+        assert_eq!(
+            actual, expected,
+            "case {}: {:?} of {}x{} grid {:?}",
+            case, window, grid.rows, grid.cols, grid.data
+        );
+    }
+}
+
+// A peak not flagged as edge-affected keeps the prominence and col it has
+// in the whole grid
+#[test]
+fn unflagged_peaks_match_whole_grid() {
+    let mut rng = Rng::new(4646);
+    let mut unflagged = 0;
+    for case in 0..5000 {
+        let grid = random_grid(&mut rng, -5..=40);
+        let window = random_window(&mut rng, &grid);
+        let whole = compute_prominence(&grid, &options()).peaks;
+        for peak in windowed_peaks(&grid, &window) {
+            if peak.edge_affected {
+                continue;
+            }
+            unflagged += 1;
+            let full = whole.iter().find(|p| position(p) == position(&peak));
+            assert_eq!(
+                full,
+                Some(&peak),
+                "case {}: {:?} of {}x{} grid {:?}",
+                case,
+                window,
+                grid.rows,
+                grid.cols,
+                grid.data
+            );
+        }
+    }
+    assert!(
+        unflagged > 300,
+        "only {} unflagged peaks checked",
+        unflagged
+    );
+}
+
+#[test]
+fn unflagged_peaks_match_whole_terrain() {
+    let grid = synth::generate(&TerrainOptions {
+        terrain: Terrain::Noise,
+        rows: 200,
+        cols: 160,
+        seed: 7,
+        ..TerrainOptions::default()
+    })
+    .unwrap();
+    let whole = compute_prominence(&grid, &options()).peaks;
+    let window = Window::from_ranges((40, 139), (30, 109), (grid.rows, grid.cols)).unwrap();
+    let peaks = windowed_peaks(&grid, &window);
+    assert!(peaks.iter().any(|p| p.edge_affected));
+    assert!(peaks.iter().any(|p| !p.edge_affected));
+    for peak in peaks.iter().filter(|p| !p.edge_affected) {
+        let full = whole.iter().find(|p| position(p) == position(peak));
+        assert_eq!(full, Some(peak));
+    }
+}
+
+#[test]
+fn edge_flags() {
+    // The 6 reaches the right side through the 5s; the 4 is cut off by 1s
+    #[rustfmt::skip]
+    let grid = Grid::new(5, 6, vec![
+        0, 0, 0, 0, 0, 0,
+        0, 9, 1, 6, 5, 5,
+        0, 1, 1, 1, 1, 0,
+        0, 4, 1, 0, 0, 0,
+        0, 0, 0, 0, 0, 0,
+    ])
+    .unwrap();
+    let open = Edges {
+        right: true,
+        ..Edges::default()
+    };
+    let flags: Vec<(usize, usize, bool)> = compute_prominence(&grid, &options().open_edges(open))
+        .peaks
+        .iter()
+        .map(|p| (p.peak_x, p.peak_y, p.edge_affected))
+        .collect();
+    assert_eq!(flags, vec![(1, 1, true), (1, 3, true), (3, 1, false)]);
+
+    // Without the middle column the 9 no longer reaches the right side
+    let mut mask = Mask::new(5, 6);
+    for cell in (0..30).filter(|cell| cell % 6 != 2) {
+        mask.insert(cell);
+    }
+    let flags: Vec<(usize, usize, bool)> =
+        compute_prominence(&grid, &options().open_edges(open).within(mask))
+            .peaks
+            .iter()
+            .map(|p| (p.peak_x, p.peak_y, p.edge_affected))
+            .collect();
+    assert_eq!(flags, vec![(1, 1, false), (1, 3, true), (3, 1, false)]);
+
+    // A window covering the whole grid has no open sides
+    let window = Window::from_ranges((0, 4), (0, 5), (5, 6)).unwrap();
+    assert!(!window.open_edges(5, 6).any());
+    assert!(windowed_peaks(&grid, &window)
+        .iter()
+        .all(|p| !p.edge_affected));
+}
+
+#[test]
+fn window_bounds() {
+    assert!(Window::from_ranges((2, 1), (0, 0), (5, 5)).is_err());
+    assert!(Window::from_ranges((0, 5), (0, 0), (5, 5)).is_err());
+    let window = Window::from_ranges((1, 3), (0, 4), (5, 5)).unwrap();
+    assert_eq!(
+        window.open_edges(5, 5),
+        Edges {
+            top: true,
+            bottom: true,
+            left: false,
+            right: false,
+        }
+    );
+}
+
+#[test]
+fn bbox_selects_cells_by_centre() {
+    // 10x10 cells of 0.1 degrees from 10E, 50N
+    let georef = Georef {
+        west: 10.0,
+        north: 50.0,
+        cell_width: 0.1,
+        cell_height: 0.1,
+    };
+    let window = Window::from_bbox(&georef, (10.2, 49.3, 10.51, 49.81), (10, 10)).unwrap();
+    assert_eq!(
+        window,
+        Window {
+            row: 2,
+            col: 2,
+            rows: 5,
+            cols: 3,
+        }
+    );
+    let shifted = window.georef(&georef);
+    assert!((shifted.west - 10.2).abs() < 1e-9 && (shifted.north - 49.8).abs() < 1e-9);
+
+    // Boxes beyond the grid are clipped; boxes missing it are refused
+    let window = Window::from_bbox(&georef, (9.0, 40.0, 10.16, 60.0), (10, 10)).unwrap();
+    assert_eq!(
+        (window.row, window.col, window.rows, window.cols),
+        (0, 0, 10, 2)
+    );
+    assert!(Window::from_bbox(&georef, (20.0, 40.0, 21.0, 41.0), (10, 10)).is_err());
+}
+
+#[test]
+fn binary_window_reads_only_the_window() {
+    let mut data: Vec<i32> = (0..7 * 9).map(|i| i * 37 % 500 - 100).collect();
+    data[0] = -32768;
+    let grid = Grid::new(7, 9, data).unwrap();
+    let path = std::env::temp_dir().join(format!("window-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    write_grid(path, &grid, None, None).unwrap();
+
+    let window = Window::from_ranges((0, 4), (2, 7), (7, 9)).unwrap();
+    let registry = Registry::default();
+    for raw in [false, true] {
+        let mut source = registry.open(path).unwrap();
+        source.set_raw(raw);
+        let mut expected = crop_window(&grid, &window);
+        if !raw {
+            expected.data.iter_mut().for_each(|v| *v = (*v).max(0));
+        }
+        let wide = source
+            .read_window(window.row, window.col, window.rows, window.cols)
+            .unwrap();
+        assert_eq!(wide, expected);
+        let compact = source.read_compact_window(&window).unwrap();
+        assert_eq!(compact.to_grid(), expected);
+    }
+    let mut source = registry.open(path).unwrap();
+    assert!(source.read_window(3, 0, 5, 9).is_err());
+    std::fs::remove_file(path).unwrap();
+}